@@ -1,26 +1,50 @@
-use std::{collections::BTreeSet, fmt, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, LazyLock},
+};
 
-use chrono::Utc;
-use git2::{BranchType, Oid};
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use git2::{BranchType, ErrorCode, Oid, Repository};
+use lockable::LockPool;
 use octocrab::models::IssueState;
-use teloxide::types::{ChatId, Message};
-use tokio::{fs::read_dir, sync::Mutex};
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    types::{ChatId, Message, MessageId, UserId},
+    utils::markdown,
+};
+use tokio::{
+    fs::{create_dir_all, read_dir, remove_dir_all, rename},
+    sync::Mutex,
+};
 
 use crate::{
     chat::{
         paths::ChatRepoPaths,
-        resources::ChatRepoResources,
+        resources::{ChatRepoResources, ChatResources},
         results::{
-            BranchCheckResult, BranchResults, CommitCheckResult, CommitResults, PRIssueCheckResult,
+            BranchChange, BranchCheckResult, BranchCommit, BranchDiffStat, BranchPairCheckResult,
+            BranchPairEvent, BranchPairResults, BranchResults, BranchStale, ChatRepoResults,
+            CommitCheckResult, CommitResults, DeferredNotification, DigestEvent, HistoryEvent,
+            HistoryKind, ImportReport, PRIssueCheckResult, PipelineProgress, WatchCheckResult,
+        },
+        settings::{
+            BranchPairSettings, BranchSettings, ChatRepoSettings, CommitSettings,
+            DashboardSettings, DigestSettings, MuteSettings, NotifySettings, PRIssueSettings,
+            QuietMode, QuietSettings, QuotaKind, QuotaSettings, Role, WatchSettings,
         },
-        settings::{BranchSettings, CommitSettings, NotifySettings, PRIssueSettings},
     },
     condition::{Action, Condition},
     error::Error,
     github::{self, GitHubInfo},
+    i18n::Language,
     options,
-    repo::{cache::query_cache_commit, resources::RepoResources},
-    utils::empty_or_start_new_line,
+    repo::{self, cache::query_cache_commit, resources::RepoResources},
+    template::TemplateKind,
+    utils::{empty_or_start_new_line, write_json},
 };
 
 pub mod paths;
@@ -67,6 +91,10 @@ pub async fn repos(chat: ChatId) -> Result<BTreeSet<String>, Error> {
         let mut results = BTreeSet::new();
         let mut dir = read_dir(&directory).await?;
         while let Some(entry) = dir.next_entry().await? {
+            // skip chat-level files
+            if !entry.file_type().await?.is_dir() {
+                continue;
+            }
             results.insert(
                 entry
                     .file_name()
@@ -78,6 +106,81 @@ pub async fn repos(chat: ChatId) -> Result<BTreeSet<String>, Error> {
     }
 }
 
+// held while updating a chat, so that its data is not moved away in the middle
+static UPDATE_LOCKS: LazyLock<LockPool<ChatId>> = LazyLock::new(LockPool::new);
+
+pub async fn update_lock(chat: ChatId) -> impl Drop {
+    UPDATE_LOCKS.async_lock(chat).await
+}
+
+pub fn allowed(chat: ChatId) -> bool {
+    ChatRepoPaths::outer_dir(chat).is_dir()
+}
+
+// returns `false` if the chat is already allowed
+pub async fn allow(chat: ChatId) -> Result<bool, Error> {
+    if allowed(chat) {
+        return Ok(false);
+    }
+    create_dir_all(ChatRepoPaths::outer_dir(chat)).await?;
+    log::info!("chat {chat} allowed");
+    Ok(true)
+}
+
+// the data of the chat is moved to the archive directory unless `delete`, returns the archive path
+pub async fn deny(chat: ChatId, delete: bool) -> Result<Option<PathBuf>, Error> {
+    let _guard = update_lock(chat).await;
+    if !allowed(chat) {
+        return Err(Error::NotInAllowList(chat));
+    }
+    evict(chat).await;
+    let directory = ChatRepoPaths::outer_dir(chat);
+    if delete {
+        remove_dir_all(&directory).await?;
+        log::info!("chat {chat} denied, data deleted");
+        return Ok(None);
+    }
+    let archive_outer = &paths::GLOBAL_CHATS_ARCHIVE;
+    create_dir_all(archive_outer.as_path()).await?;
+    let name = directory.file_name().unwrap_or_default().to_string_lossy();
+    let archive = archive_outer.join(format!("{name}-{}", Utc::now().format("%Y%m%d%H%M%S")));
+    rename(&directory, &archive).await?;
+    log::info!("chat {chat} denied, data archived to {archive:?}");
+    Ok(Some(archive))
+}
+
+// drops cached resources of the chat, so that its directory can be moved or deleted
+async fn evict(chat: ChatId) {
+    let tasks: Vec<Task> = resources::RESOURCES_MAP
+        .map
+        .lock()
+        .await
+        .keys()
+        .filter(|task| task.chat == chat)
+        .cloned()
+        .collect();
+    for task in tasks {
+        if let Err(e) = resources::RESOURCES_MAP
+            .remove(&task, async |_| Ok(()))
+            .await
+        {
+            log::warn!("failed to drop resources of {task}: {e}");
+        }
+    }
+    let cached = resources::CHAT_RESOURCES_MAP
+        .map
+        .lock()
+        .await
+        .contains_key(&chat);
+    if cached
+        && let Err(e) = resources::CHAT_RESOURCES_MAP
+            .remove(&chat, async |_| Ok(()))
+            .await
+    {
+        log::warn!("failed to drop resources of chat {chat}: {e}");
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Task {
     pub chat: ChatId,
@@ -90,10 +193,129 @@ impl fmt::Display for Task {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Item {
+    Commit(String),
+    Branch(String),
+    PRIssue(u64),
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Item::Commit(hash) => write!(f, "commit {hash}"),
+            Item::Branch(branch) => write!(f, "branch {branch}"),
+            Item::PRIssue(id) => write!(f, "PR/issue {id}"),
+        }
+    }
+}
+
+impl Item {
+    pub fn category(&self) -> &'static str {
+        match self {
+            Item::Commit(_) => "commits",
+            Item::Branch(_) => "branches",
+            Item::PRIssue(_) => "PRs/issues",
+        }
+    }
+
+    pub fn unknown_error(&self) -> Error {
+        match self {
+            Item::Commit(hash) => Error::UnknownCommit(hash.clone()),
+            Item::Branch(branch) => Error::UnknownBranch(branch.clone()),
+            Item::PRIssue(id) => Error::UnknownPRIssue(*id),
+        }
+    }
+}
+
 pub async fn resources(task: &Task) -> Result<Arc<ChatRepoResources>, Error> {
     resources::RESOURCES_MAP.get(task).await
 }
 
+pub async fn chat_resources(chat: ChatId) -> Result<Arc<ChatResources>, Error> {
+    resources::CHAT_RESOURCES_MAP.get(&chat).await
+}
+
+pub async fn language(chat: ChatId) -> Language {
+    match chat_resources(chat).await {
+        Ok(chat_resources) => chat_resources.settings.read().await.language,
+        Err(e) => {
+            log::debug!("failed to get language of chat {chat}: {e}");
+            Language::default()
+        }
+    }
+}
+
+pub async fn language_set(chat_resources: &ChatResources, language: Language) -> Result<(), Error> {
+    {
+        let mut settings = chat_resources.settings.write().await;
+        settings.language = language;
+    }
+    chat_resources.save_settings().await
+}
+
+// clear the explicit role if none is given
+pub async fn role_set(
+    chat_resources: &ChatResources,
+    user: UserId,
+    role: Option<Role>,
+) -> Result<(), Error> {
+    {
+        let mut settings = chat_resources.settings.write().await;
+        match role {
+            Some(role) => settings.roles.insert(user, role),
+            None => settings.roles.remove(&user),
+        };
+    }
+    chat_resources.save_settings().await
+}
+
+pub async fn default_role_set(chat_resources: &ChatResources, role: Role) -> Result<(), Error> {
+    {
+        let mut settings = chat_resources.settings.write().await;
+        settings.default_role = role;
+    }
+    chat_resources.save_settings().await
+}
+
+// the chat's own template takes precedence over the repository default
+pub async fn template(
+    chat: ChatId,
+    repo_resources: &RepoResources,
+    kind: TemplateKind,
+) -> Option<String> {
+    match chat_resources(chat).await {
+        Ok(chat_resources) => {
+            if let Some(template) = chat_resources.settings.read().await.templates.get(&kind) {
+                return Some(template.clone());
+            }
+        }
+        Err(e) => log::debug!("failed to get templates of chat {chat}: {e}"),
+    }
+    repo_resources
+        .settings
+        .read()
+        .await
+        .templates
+        .get(&kind)
+        .cloned()
+}
+
+pub async fn template_set(
+    chat_resources: &ChatResources,
+    kind: TemplateKind,
+    template: Option<String>,
+) -> Result<(), Error> {
+    {
+        let mut settings = chat_resources.settings.write().await;
+        match template {
+            Some(template) => settings.templates.insert(kind, template),
+            None => settings.templates.remove(&kind),
+        };
+    }
+    chat_resources.save_settings().await
+}
+
 pub async fn resources_chat_repo(
     chat: ChatId,
     repo: String,
@@ -110,10 +332,127 @@ pub async fn resources_msg_repo(
     resources_chat_repo(chat, repo).await
 }
 
+pub async fn quota_limit(chat: ChatId, kind: QuotaKind) -> Result<usize, Error> {
+    let chat_resources = chat_resources(chat).await?;
+    let quota = chat_resources.settings.read().await.quota.get(kind);
+    let options = options::get();
+    Ok(quota.unwrap_or(match kind {
+        QuotaKind::Commits => options.max_commits,
+        QuotaKind::PRIssues => options.max_pr_issues,
+        QuotaKind::Branches => options.max_branches,
+    }))
+}
+
+// counted over all repositories of the chat
+async fn quota_used(chat: ChatId, kind: QuotaKind) -> Result<usize, Error> {
+    quota_used_except(chat, &[], kind).await
+}
+
+async fn quota_used_except(chat: ChatId, except: &[&str], kind: QuotaKind) -> Result<usize, Error> {
+    let mut used = 0;
+    for repo in repos(chat).await? {
+        if except.contains(&repo.as_str()) {
+            continue;
+        }
+        let resources = resources_chat_repo(chat, repo).await?;
+        used += kind.count(&*resources.settings.read().await);
+    }
+    Ok(used)
+}
+
+// (kind, used, limit)
+pub async fn quota_usage(chat: ChatId) -> Result<Vec<(QuotaKind, usize, usize)>, Error> {
+    let mut usage = Vec::new();
+    for kind in QuotaKind::ALL {
+        usage.push((
+            kind,
+            quota_used(chat, kind).await?,
+            quota_limit(chat, kind).await?,
+        ));
+    }
+    Ok(usage)
+}
+
+async fn quota_ensure(chat: ChatId, kind: QuotaKind) -> Result<(), Error> {
+    let used = quota_used(chat, kind).await?;
+    let limit = quota_limit(chat, kind).await?;
+    if used >= limit {
+        Err(Error::QuotaExceeded { kind, used, limit })
+    } else {
+        Ok(())
+    }
+}
+
+// held from the quota check until the checked settings are stored
+static QUOTA_LOCKS: LazyLock<LockPool<ChatId>> = LazyLock::new(LockPool::new);
+
+// applies `edit` to a copy of the settings, which is only kept if it does not grow any usage past
+// its quota
+async fn quota_edit<T>(
+    resources: &ChatRepoResources,
+    edit: impl FnOnce(&mut ChatRepoSettings) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let chat = resources.task.chat;
+    let _guard = QUOTA_LOCKS.async_lock(chat).await;
+    // (kind, used by other repositories, limit)
+    let mut quotas = Vec::new();
+    for kind in QuotaKind::ALL {
+        quotas.push((
+            kind,
+            quota_used_except(chat, &[&resources.task.repo], kind).await?,
+            quota_limit(chat, kind).await?,
+        ));
+    }
+    let output = {
+        let mut locked = resources.settings.write().await;
+        let mut edited = locked.clone();
+        let output = edit(&mut edited)?;
+        for (kind, others, limit) in quotas {
+            let (before, after) = (kind.count(&locked), kind.count(&edited));
+            if after > before && others + after > limit {
+                return Err(Error::QuotaExceeded {
+                    kind,
+                    used: others + before,
+                    limit,
+                });
+            }
+        }
+        *locked = edited;
+        output
+    };
+    resources.save_settings().await?;
+    Ok(output)
+}
+
+pub async fn chat_quota_set(chat: ChatId, quota: QuotaSettings) -> Result<(), Error> {
+    let chat_resources = chat_resources(chat).await?;
+    {
+        let mut settings = chat_resources.settings.write().await;
+        settings.quota = quota;
+    }
+    chat_resources.save_settings().await
+}
+
 pub async fn commit_add(
     resources: &ChatRepoResources,
     hash: &str,
     settings: CommitSettings,
+) -> Result<(), Error> {
+    quota_edit(resources, |locked| {
+        if locked.commits.contains_key(hash) {
+            return Err(Error::CommitExists(hash.to_owned()));
+        }
+        locked.commits.insert(hash.to_owned(), settings);
+        Ok(())
+    })
+    .await
+}
+
+// commits of merged PRs replace them and are not limited by the quota
+async fn commit_insert(
+    resources: &ChatRepoResources,
+    hash: &str,
+    settings: CommitSettings,
 ) -> Result<(), Error> {
     {
         let mut locked = resources.settings.write().await;
@@ -150,6 +489,12 @@ pub async fn commit_check(
     log::info!("checking commit ({task}, {hash})", task = resources.task);
     if let Err(e) = commit_pre_check(repo_resources, hash).await {
         commit_remove(resources, hash).await?;
+        let summary = format!(
+            "commit `{}` removed: {}",
+            markdown::escape(hash),
+            markdown::escape(&e.to_string())
+        );
+        history_record(resources, HistoryKind::Removed, summary).await;
         return Err(e);
     }
     let cache = repo_resources.cache().await?;
@@ -160,15 +505,14 @@ pub async fn commit_check(
             .await
             .map_err(|e| Error::DBInteract(Mutex::new(e)))??
     };
-    let new_results = CommitResults {
-        branches: all_branches.clone(),
-    };
     let old_results = {
         let mut results = resources.results.write().await;
-        results
-            .commits
-            .insert(hash.to_string(), new_results)
-            .unwrap_or_default()
+        let entry = results.commits.entry(hash.to_string()).or_default();
+        let new_results = CommitResults {
+            branches: all_branches.clone(),
+            message: entry.message,
+        };
+        std::mem::replace(entry, new_results)
     };
     let new_branches = all_branches
         .difference(&old_results.branches)
@@ -178,10 +522,12 @@ pub async fn commit_check(
         all: all_branches,
         new: new_branches,
         conditions: Default::default(),
+        pipeline: None,
     };
     let mut remove = false;
     {
         let settings = repo_resources.settings.read().await;
+        check_result.pipeline = PipelineProgress::new(&settings.pipeline, &check_result.all);
         for (condition_name, condition_setting) in &settings.conditions {
             let action = condition_setting.condition.check(&check_result);
             if action.is_none() {
@@ -196,11 +542,30 @@ pub async fn commit_check(
             }
         }
     }
+    if let Some(pipeline) = &mut check_result.pipeline {
+        pipeline.etas =
+            repo::pipeline_etas(repo_resources, &pipeline.stages, pipeline.reached, hash).await?;
+    }
     if remove {
-        let mut settings = resources.settings.write().await;
-        let mut results = resources.results.write().await;
-        settings.commits.remove(hash);
-        results.commits.remove(hash);
+        {
+            let mut settings = resources.settings.write().await;
+            let mut results = resources.results.write().await;
+            settings.commits.remove(hash);
+            results.commits.remove(hash);
+        }
+        let summary = format!(
+            "commit `{}` removed by conditions: {}",
+            markdown::escape(hash),
+            markdown::escape(
+                &check_result
+                    .conditions_of_action(Action::Remove)
+                    .into_iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        );
+        history_record(resources, HistoryKind::Removed, summary).await;
     }
     resources.save_settings().await?;
     resources.save_results().await?;
@@ -227,16 +592,17 @@ pub async fn pr_issue_add(
             .clone()
             .ok_or(Error::NoGitHubInfo(resources.task.repo.clone()))?
     };
+    quota_ensure(resources.task.chat, QuotaKind::PRIssues).await?;
     // every PR id is also an issue id
     let _pr = github::get_issue(&github_info, id).await?; // ensure the PR/issue id is real
-    {
-        let mut locked = resources.settings.write().await;
+    quota_edit(resources, |locked| {
         if locked.pr_issues.contains_key(&id) {
             return Err(Error::PRIssueExists(id));
         }
         locked.pr_issues.insert(id, settings);
-    }
-    resources.save_settings().await
+        Ok(())
+    })
+    .await
 }
 
 pub async fn pr_issue_remove(resources: &ChatRepoResources, id: u64) -> Result<(), Error> {
@@ -318,6 +684,8 @@ pub async fn pr_issue_check(
                                     .ok_or(Error::UnknownPRIssue(id))?;
                                 drop(locked);
                                 resources.save_settings().await?;
+                                let summary = format!("PR/issue {id} expired after being closed");
+                                history_record(resources, HistoryKind::Expired, summary).await;
                                 Ok(PRIssueCheckResult::Expired)
                             } else {
                                 // not expired
@@ -357,13 +725,16 @@ pub async fn merged_pr_to_commit(
     );
     let commit_settings = CommitSettings {
         url: Some(settings.url),
+        living: false,
         notify: NotifySettings {
             comment,
             subscribers: settings.notify.subscribers,
+            mute: settings.notify.mute,
         },
     };
 
-    commit_add(resources, &commit, commit_settings)
+    // the PR is replaced by its commit
+    commit_insert(resources, &commit, commit_settings)
         .await
         .map(|()| commit)
 }
@@ -373,14 +744,14 @@ pub async fn branch_add(
     branch: &str,
     settings: BranchSettings,
 ) -> Result<(), Error> {
-    {
-        let mut locked = resources.settings.write().await;
+    quota_edit(resources, |locked| {
         if locked.branches.contains_key(branch) {
             return Err(Error::BranchExists(branch.to_owned()));
         }
         locked.branches.insert(branch.to_owned(), settings);
-    }
-    resources.save_settings().await
+        Ok(())
+    })
+    .await
 }
 
 pub async fn branch_remove(resources: &ChatRepoResources, branch: &str) -> Result<(), Error> {
@@ -417,15 +788,27 @@ pub async fn branch_check(
             }
         };
 
+        let (paths, diff_stat, stale_settings) = {
+            let settings = resources.settings.read().await;
+            settings
+                .branches
+                .get(branch_name)
+                .map(|b| (b.paths.clone(), b.diff_stat, b.stale.clone()))
+                .unwrap_or_default()
+        };
+
+        let mut filtered = !paths.is_empty();
+        let mut head_time = None;
         // get the new commit (optional)
-        let commit = {
+        let (commit, change, (commits, truncated, unlisted_touching), diff_stat) = {
             let repo = repo_resources.repo.lock().await;
             let remote_branch_name = format!("origin/{branch_name}");
 
-            match repo.find_branch(&remote_branch_name, BranchType::Remote) {
+            let commit = match repo.find_branch(&remote_branch_name, BranchType::Remote) {
                 Ok(branch) => {
-                    let commit: String = branch.into_reference().peel_to_commit()?.id().to_string();
-                    Some(commit)
+                    let commit = branch.into_reference().peel_to_commit()?;
+                    head_time = DateTime::from_timestamp(commit.time().seconds(), 0);
+                    Some(commit.id().to_string())
                 }
                 Err(_error) => {
                     log::warn!(
@@ -436,15 +819,81 @@ pub async fn branch_check(
                     );
                     None
                 }
+            };
+
+            let change = branch_change(&repo, old_result.commit.as_deref(), commit.as_deref())?;
+
+            // only list commits when the new commit shares history with the old one
+            match (&old_result.commit, &commit, &change) {
+                (
+                    Some(old),
+                    Some(new),
+                    BranchChange::FastForward | BranchChange::ForcePush { .. },
+                ) => {
+                    let (old, new) = (Oid::from_str(old)?, Oid::from_str(new)?);
+                    let listed = match branch_commits(&repo, old, new, &paths) {
+                        Ok(commits) => commits,
+                        Err(e) if filtered && repo::is_missing_object(&e) => {
+                            warn_missing_trees(resources, branch_name, &e);
+                            // fall back to notifying every head change
+                            filtered = false;
+                            branch_commits(&repo, old, new, &[])?
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    let diff_stat = if diff_stat {
+                        match branch_diff_stat(&repo, old, new, &paths) {
+                            Ok(stat) => Some(stat),
+                            Err(e) if repo::is_missing_object(&e) => {
+                                warn_missing_trees(resources, branch_name, &e);
+                                None
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    } else {
+                        None
+                    };
+                    (commit, change, listed, diff_stat)
+                }
+                _ => (commit, change, (Vec::new(), false, false), None),
             }
         };
 
+        let now = Utc::now();
+        // the branch may already be stale when it is checked the first time
+        let first_check = old_result.changed_at.is_none();
+        let mut changed_at = old_result.changed_at.or(head_time).unwrap_or(now);
+        let mut stale_alerted_at = old_result.stale_alerted_at;
+        let mut stale = None;
+        if !first_check && !matches!(change, BranchChange::Unchanged) {
+            if stale_alerted_at.is_some() {
+                stale = Some(BranchStale::MovingAgain(changed_at));
+            }
+            changed_at = now;
+            stale_alerted_at = None;
+        } else if let Some(stale_settings) = &stale_settings {
+            let elapsed = |since: DateTime<Utc>| (now - since).to_std().unwrap_or_default();
+            let alert = elapsed(changed_at) >= stale_settings.after
+                && match (stale_alerted_at, stale_settings.repeat) {
+                    (None, _) => true,
+                    (Some(alerted_at), Some(repeat)) => elapsed(alerted_at) >= repeat,
+                    (Some(_), None) => false,
+                };
+            if alert {
+                stale = Some(BranchStale::Stale(changed_at));
+                stale_alerted_at = Some(now);
+            }
+        }
+
         {
             let mut results = resources.results.write().await;
             results.branches.insert(
                 branch_name.to_owned(),
                 BranchResults {
                     commit: commit.clone(),
+                    changed_at: Some(changed_at),
+                    stale_alerted_at,
+                    message: old_result.message,
                 },
             );
         }
@@ -453,7 +902,895 @@ pub async fn branch_check(
         BranchCheckResult {
             old: old_result.commit,
             new: commit,
+            change,
+            commits,
+            truncated,
+            filtered,
+            unlisted_touching,
+            diff_stat,
+            stale,
+        }
+    };
+    Ok(result)
+}
+
+// listing stops here, every listed commit may be diffed for path filters
+pub const MAX_BRANCH_COMMITS: usize = 1000;
+
+// (commits, truncated, unlisted commits touch the paths)
+fn branch_commits(
+    repo: &Repository,
+    old: Oid,
+    new: Oid,
+    paths: &[String],
+) -> Result<(Vec<BranchCommit>, bool, bool), Error> {
+    let (ids, truncated) = repo::commits_between(repo, old, new, MAX_BRANCH_COMMITS)?;
+    let mut commits = Vec::new();
+    for id in ids {
+        let c = repo.find_commit(id)?;
+        if paths.is_empty() || repo::commit_touches_paths(repo, &c, paths)? {
+            commits.push(BranchCommit::from_git(&c));
+        }
+    }
+    // commits past the limit are not checked one by one, a single diff of the whole change tells
+    // whether any of them may touch the paths
+    let unlisted_touching = truncated && !paths.is_empty() && commits.is_empty() && {
+        let base = repo.merge_base(old, new)?;
+        repo::diff_commits(repo, base, new, paths)?.deltas().len() != 0
+    };
+    Ok((commits, truncated, unlisted_touching))
+}
+
+fn warn_missing_trees(resources: &ChatRepoResources, branch: &str, error: &Error) {
+    log::warn!(
+        "trees are missing in repository {repo}, ignoring path filters and diffstat of branch {branch} in {chat}, \
+         the clone is probably made with `--filter=tree:0`, \
+         run `git fetch --refetch --filter=blob:none` in it to fix: {error}",
+        repo = resources.task.repo,
+        chat = resources.task.chat,
+    );
+}
+
+fn branch_change(
+    repo: &Repository,
+    old: Option<&str>,
+    new: Option<&str>,
+) -> Result<BranchChange, Error> {
+    let (old, new) = match (old, new) {
+        (old, new) if old == new => return Ok(BranchChange::Unchanged),
+        (None, Some(_)) => return Ok(BranchChange::Created),
+        (Some(_), None) => return Ok(BranchChange::Deleted),
+        (Some(old), Some(new)) => (Oid::from_str(old)?, Oid::from_str(new)?),
+        (None, None) => unreachable!(),
+    };
+    match repo.find_commit(old) {
+        Ok(_) => {}
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(BranchChange::UnknownBase),
+        Err(e) => return Err(e.into()),
+    }
+    match repo.merge_base(old, new) {
+        Ok(base) if base == old => Ok(BranchChange::FastForward),
+        Ok(base) => {
+            let (dropped, added) = repo.graph_ahead_behind(old, new)?;
+            Ok(BranchChange::ForcePush {
+                merge_base: base.to_string(),
+                dropped,
+                added,
+            })
+        }
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(BranchChange::Recreate),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn branch_diff_stat(
+    repo: &Repository,
+    old: Oid,
+    new: Oid,
+    paths: &[String],
+) -> Result<BranchDiffStat, Error> {
+    let diff = repo::diff_commits(repo, old, new, paths)?;
+    Ok(BranchDiffStat {
+        files: diff.deltas().len(),
+    })
+}
+
+pub async fn branch_pair_add(
+    resources: &ChatRepoResources,
+    settings: BranchPairSettings,
+) -> Result<(), Error> {
+    let key = BranchPairSettings::key(&settings.branch, &settings.base);
+    {
+        let mut locked = resources.settings.write().await;
+        if locked.branch_pairs.contains_key(&key) {
+            return Err(Error::BranchPairExists(key));
+        }
+        locked.branch_pairs.insert(key, settings);
+    }
+    resources.save_settings().await
+}
+
+pub async fn branch_pair_remove(resources: &ChatRepoResources, key: &str) -> Result<(), Error> {
+    {
+        let mut locked = resources.settings.write().await;
+        if !locked.branch_pairs.contains_key(key) {
+            return Err(Error::UnknownBranchPair(key.to_owned()));
+        }
+        locked.branch_pairs.remove(key);
+    }
+    {
+        let mut locked = resources.results.write().await;
+        locked.branch_pairs.remove(key);
+    }
+    resources.save_settings().await?;
+    resources.save_results().await
+}
+
+pub async fn branch_compare(
+    repo_resources: &RepoResources,
+    branch: &str,
+    base: &str,
+) -> Result<BranchPairResults, Error> {
+    let repo = repo_resources.repo.lock().await;
+    let branch_commit = repo::branch_commit(&repo, branch)?.id();
+    let base_commit = repo::branch_commit(&repo, base)?.id();
+    let (ahead, behind) = repo.graph_ahead_behind(branch_commit, base_commit)?;
+    Ok(BranchPairResults { ahead, behind })
+}
+
+pub async fn branch_pair_check(
+    resources: &ChatRepoResources,
+    repo_resources: &RepoResources,
+    key: &str,
+) -> Result<BranchPairCheckResult, Error> {
+    log::info!(
+        "checking branch pair ({task}, {key})",
+        task = resources.task
+    );
+    let settings = {
+        let locked = resources.settings.read().await;
+        locked
+            .branch_pairs
+            .get(key)
+            .ok_or_else(|| Error::UnknownBranchPair(key.to_owned()))?
+            .clone()
+    };
+    let new = branch_compare(repo_resources, &settings.branch, &settings.base).await?;
+    let old = {
+        let mut results = resources.results.write().await;
+        results.branch_pairs.insert(key.to_owned(), new)
+    };
+    resources.save_results().await?;
+    let event = BranchPairEvent::between(old.map(|old| old.behind), new.behind, settings.threshold);
+    Ok(BranchPairCheckResult { new, event })
+}
+
+pub async fn watch_add(
+    resources: &ChatRepoResources,
+    identifier: &str,
+    settings: WatchSettings,
+) -> Result<(), Error> {
+    {
+        let mut locked = resources.settings.write().await;
+        if locked.watches.contains_key(identifier) {
+            return Err(Error::WatchExists(identifier.to_owned()));
+        }
+        locked.watches.insert(identifier.to_owned(), settings);
+    }
+    resources.save_settings().await
+}
+
+pub async fn watch_remove(resources: &ChatRepoResources, identifier: &str) -> Result<(), Error> {
+    {
+        let mut locked = resources.settings.write().await;
+        if !locked.watches.contains_key(identifier) {
+            return Err(Error::UnknownWatch(identifier.to_owned()));
+        }
+        locked.watches.remove(identifier);
+    }
+    resources.save_settings().await
+}
+
+pub async fn watch_check(
+    resources: &ChatRepoResources,
+    repo_resources: &RepoResources,
+    commits: &BTreeSet<String>,
+) -> Result<Vec<WatchCheckResult>, Error> {
+    log::info!("checking watch rules ({task})", task = resources.task);
+    let watches = {
+        let settings = resources.settings.read().await;
+        settings.watches.clone()
+    };
+    if watches.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut matched = Vec::new();
+    {
+        let repo = repo_resources.repo.lock().await;
+        for hash in commits {
+            let commit = repo.find_commit(Oid::from_str(hash)?)?;
+            let message = String::from_utf8_lossy(commit.message_bytes());
+            if let Some((watch, watch_settings)) = watches
+                .iter()
+                .find(|(_, w)| w.message_regex.is_match(&message))
+            {
+                let comment = format!(
+                    "{summary}{comment}",
+                    summary = String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default()),
+                    comment = empty_or_start_new_line(&watch_settings.notify.comment),
+                );
+                matched.push(WatchCheckResult {
+                    watch: watch.clone(),
+                    commit: hash.clone(),
+                    settings: CommitSettings {
+                        url: None,
+                        living: false,
+                        notify: NotifySettings {
+                            comment,
+                            subscribers: watch_settings.notify.subscribers.clone(),
+                            mute: None,
+                        },
+                    },
+                });
+            }
+        }
+    }
+    let mut results = Vec::new();
+    for m in matched {
+        match commit_add(resources, &m.commit, m.settings.clone()).await {
+            Ok(()) => results.push(m),
+            // already tracked manually
+            Err(Error::CommitExists(_)) => continue,
+            // the rest is left untracked rather than growing past the quota
+            Err(Error::QuotaExceeded { kind, used, limit }) => {
+                log::warn!(
+                    "watch rule {watch} skipped commit {commit} ({task}): {kind} quota exceeded ({used}/{limit})",
+                    watch = m.watch,
+                    commit = m.commit,
+                    task = resources.task,
+                );
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(results)
+}
+
+pub async fn chat_mute(
+    chat_resources: &ChatResources,
+    until: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    {
+        let mut settings = chat_resources.settings.write().await;
+        settings.mute.get_or_insert_with(Default::default).until = until;
+    }
+    chat_resources.save_settings().await
+}
+
+pub async fn chat_unmute(chat_resources: &ChatResources) -> Result<MuteSettings, Error> {
+    let mute = {
+        let mut settings = chat_resources.settings.write().await;
+        settings.mute.take().ok_or(Error::NotMuted)?
+    };
+    chat_resources.save_settings().await?;
+    Ok(mute)
+}
+
+pub async fn item_mute(
+    resources: &ChatRepoResources,
+    item: &Item,
+    until: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    {
+        let mut settings = resources.settings.write().await;
+        let notify = settings
+            .notify_mut(item)
+            .ok_or_else(|| item.unknown_error())?;
+        notify.mute.get_or_insert_with(Default::default).until = until;
+    }
+    resources.save_settings().await
+}
+
+pub async fn item_unmute(
+    resources: &ChatRepoResources,
+    item: &Item,
+) -> Result<MuteSettings, Error> {
+    let mute = {
+        let mut settings = resources.settings.write().await;
+        let notify = settings
+            .notify_mut(item)
+            .ok_or_else(|| item.unknown_error())?;
+        notify.mute.take().ok_or(Error::NotMuted)?
+    };
+    resources.save_settings().await?;
+    Ok(mute)
+}
+
+// returns `true` if the notification is muted, the summary is recorded for later report
+pub async fn mute_record(
+    resources: &ChatRepoResources,
+    item: Option<&Item>,
+    summary: &str,
+) -> Result<bool, Error> {
+    let now = Utc::now();
+    let chat_resources = chat_resources(resources.task.chat).await?;
+    {
+        let mut settings = chat_resources.settings.write().await;
+        if let Some(mute) = settings.mute.as_mut().filter(|m| !m.expired(now)) {
+            mute.suppressed.push(summary.to_owned());
+            drop(settings);
+            chat_resources.save_settings().await?;
+            return Ok(true);
+        }
+    }
+    if let Some(item) = item {
+        let mut settings = resources.settings.write().await;
+        if let Some(mute) = settings
+            .notify_mut(item)
+            .and_then(|n| n.mute.as_mut())
+            .filter(|m| !m.expired(now))
+        {
+            mute.suppressed.push(summary.to_owned());
+            drop(settings);
+            resources.save_settings().await?;
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// expired mutes are kept until `chat_mute_clear`, so a failed summary is sent again
+pub async fn chat_mute_expired(chat_resources: &ChatResources) -> Option<MuteSettings> {
+    let now = Utc::now();
+    let settings = chat_resources.settings.read().await;
+    settings.mute.clone().filter(|m| m.expired(now))
+}
+
+pub async fn chat_mute_clear(chat_resources: &ChatResources) -> Result<(), Error> {
+    let now = Utc::now();
+    let cleared = {
+        let mut settings = chat_resources.settings.write().await;
+        settings.mute.take_if(|m| m.expired(now)).is_some()
+    };
+    if cleared {
+        chat_resources.save_settings().await?;
+    }
+    Ok(())
+}
+
+pub async fn item_mute_expired(resources: &ChatRepoResources) -> Vec<(Item, MuteSettings)> {
+    let now = Utc::now();
+    let mut settings = resources.settings.read().await.clone();
+    settings
+        .items()
+        .into_iter()
+        .filter_map(|item| {
+            let mute = settings
+                .notify_mut(&item)?
+                .mute
+                .take_if(|m| m.expired(now))?;
+            Some((item, mute))
+        })
+        .collect()
+}
+
+pub async fn item_mute_clear(resources: &ChatRepoResources, item: &Item) -> Result<(), Error> {
+    let now = Utc::now();
+    let cleared = {
+        let mut settings = resources.settings.write().await;
+        settings
+            .notify_mut(item)
+            .and_then(|n| n.mute.take_if(|m| m.expired(now)))
+            .is_some()
+    };
+    if cleared {
+        resources.save_settings().await?;
+    }
+    Ok(())
+}
+
+pub async fn export(
+    chat: ChatId,
+    repo: Option<String>,
+) -> Result<BTreeMap<String, ChatRepoSettings>, Error> {
+    let repos = match repo {
+        Some(repo) => {
+            if !repos(chat).await?.contains(&repo) {
+                return Err(Error::UnknownRepository(repo));
+            }
+            BTreeSet::from([repo])
         }
+        None => repos(chat).await?,
     };
+    let mut result = BTreeMap::new();
+    for repo in repos {
+        let resources = resources_chat_repo(chat, repo.clone()).await?;
+        let settings = resources.settings.read().await.clone();
+        result.insert(repo, settings);
+    }
     Ok(result)
 }
+
+// returns descriptions of invalid items, nothing should be imported if any
+pub async fn import_validate(
+    repo_resources: &RepoResources,
+    settings: &ChatRepoSettings,
+) -> Result<Vec<String>, Error> {
+    let repo = &repo_resources.name;
+    let mut invalid = Vec::new();
+    {
+        let git = repo_resources.repo.lock().await;
+        for hash in settings.commits.keys() {
+            let found = Oid::from_str(hash).and_then(|id| git.find_commit(id));
+            if let Err(e) = found {
+                invalid.push(format!("[{repo}] commit {hash}: {e}"));
+            }
+        }
+        for branch in settings.branches.keys() {
+            if let Err(e) = repo::branch_commit(&git, branch) {
+                invalid.push(format!("[{repo}] branch {branch}: {e}"));
+            }
+        }
+        for pair in settings.branch_pairs.values() {
+            for branch in [&pair.branch, &pair.base] {
+                if let Err(e) = repo::branch_commit(&git, branch) {
+                    invalid.push(format!("[{repo}] branch pair {branch}: {e}"));
+                }
+            }
+        }
+    }
+    if !settings.pr_issues.is_empty() {
+        let github_info = {
+            let locked = repo_resources.settings.read().await;
+            locked.github_info.clone()
+        };
+        match github_info {
+            Some(info) => {
+                for id in settings.pr_issues.keys() {
+                    if let Err(e) = github::get_issue(&info, *id).await {
+                        invalid.push(format!("[{repo}] PR/issue {id}: {e}"));
+                    }
+                }
+            }
+            None => invalid.push(format!(
+                "[{repo}] PR/issues: {}",
+                Error::NoGitHubInfo(repo.clone())
+            )),
+        }
+    }
+    Ok(invalid)
+}
+
+// all repositories of the document are imported or none, quotas are checked for the whole result
+pub async fn import(
+    chat: ChatId,
+    imported: BTreeMap<String, ChatRepoSettings>,
+    replace: bool,
+) -> Result<BTreeMap<String, ImportReport>, Error> {
+    let _guard = QUOTA_LOCKS.async_lock(chat).await;
+    let mut resources = Vec::new();
+    for repo in imported.keys() {
+        resources.push(resources_chat_repo(chat, repo.clone()).await?);
+    }
+    let involved: Vec<_> = imported.keys().map(String::as_str).collect();
+    // (kind, used by other repositories, limit)
+    let mut quotas = Vec::new();
+    for kind in QuotaKind::ALL {
+        quotas.push((
+            kind,
+            quota_used_except(chat, &involved, kind).await?,
+            quota_limit(chat, kind).await?,
+        ));
+    }
+    let mut reports = BTreeMap::new();
+    {
+        // locked in the order of the names, like the document
+        let mut locked = Vec::new();
+        for r in &resources {
+            locked.push(r.settings.write().await);
+        }
+        let mut edited = Vec::new();
+        for ((repo, settings), current) in imported.into_iter().zip(&locked) {
+            let mut new = (**current).clone();
+            reports.insert(repo, import_settings(&mut new, settings, replace));
+            edited.push(new);
+        }
+        for (kind, others, limit) in quotas {
+            let before: usize = locked.iter().map(|s| kind.count(s)).sum();
+            let after: usize = edited.iter().map(|s| kind.count(s)).sum();
+            if after > before && others + after > limit {
+                return Err(Error::QuotaExceeded {
+                    kind,
+                    used: others + before,
+                    limit,
+                });
+            }
+        }
+        // written while still locked, files already written are restored if one fails
+        for (i, (r, new)) in resources.iter().zip(&edited).enumerate() {
+            if let Err(e) = write_json(&r.paths.settings, new) {
+                for (r, current) in resources.iter().zip(&locked).take(i) {
+                    if let Err(e) = write_json(&r.paths.settings, &**current) {
+                        log::error!("failed to restore settings of {task}: {e}", task = r.task);
+                    }
+                }
+                return Err(e);
+            }
+        }
+        for (current, new) in locked.iter_mut().zip(edited) {
+            **current = new;
+        }
+    }
+    for r in &resources {
+        import_clean_results(r).await?;
+    }
+    Ok(reports)
+}
+
+fn import_settings(
+    settings: &mut ChatRepoSettings,
+    imported: ChatRepoSettings,
+    replace: bool,
+) -> ImportReport {
+    let mut report = ImportReport::default();
+    import_map(
+        &mut settings.commits,
+        imported.commits,
+        replace,
+        "commit",
+        &mut report,
+    );
+    import_map(
+        &mut settings.pr_issues,
+        imported.pr_issues,
+        replace,
+        "PR/issue",
+        &mut report,
+    );
+    import_map(
+        &mut settings.branches,
+        imported.branches,
+        replace,
+        "branch",
+        &mut report,
+    );
+    import_map(
+        &mut settings.watches,
+        imported.watches,
+        replace,
+        "watch rule",
+        &mut report,
+    );
+    import_map(
+        &mut settings.branch_pairs,
+        imported.branch_pairs,
+        replace,
+        "branch pair",
+        &mut report,
+    );
+    report
+}
+
+// drops the results of removed items
+async fn import_clean_results(resources: &ChatRepoResources) -> Result<(), Error> {
+    {
+        let settings = resources.settings.read().await;
+        let mut results = resources.results.write().await;
+        results
+            .commits
+            .retain(|hash, _| settings.commits.contains_key(hash));
+        results
+            .branches
+            .retain(|branch, _| settings.branches.contains_key(branch));
+        results
+            .branch_pairs
+            .retain(|key, _| settings.branch_pairs.contains_key(key));
+    }
+    resources.save_results().await
+}
+
+fn import_map<K, V>(
+    existing: &mut BTreeMap<K, V>,
+    imported: BTreeMap<K, V>,
+    replace: bool,
+    kind: &str,
+    report: &mut ImportReport,
+) where
+    K: Ord + fmt::Display,
+{
+    if replace {
+        existing.retain(|key, _| {
+            let keep = imported.contains_key(key);
+            if !keep {
+                report.removed.push(format!("{kind} {key}"));
+            }
+            keep
+        });
+    }
+    for (key, value) in imported {
+        let description = format!("{kind} {key}");
+        if existing.insert(key, value).is_some() {
+            report.updated.push(description);
+        } else {
+            report.added.push(description);
+        }
+    }
+}
+
+pub async fn digest_set(
+    chat_resources: &ChatResources,
+    digest: DigestSettings,
+) -> Result<(), Error> {
+    Schedule::from_str(&digest.schedule)?;
+    {
+        let mut settings = chat_resources.settings.write().await;
+        settings.digest = Some(digest);
+    }
+    {
+        let mut results = chat_resources.results.write().await;
+        results.digest.last_digest_at.get_or_insert_with(Utc::now);
+    }
+    chat_resources.save_settings().await?;
+    chat_resources.save_results().await
+}
+
+// returns messages of the dashboard to be deleted
+pub async fn dashboard_set(
+    chat_resources: &ChatResources,
+    dashboard: Option<DashboardSettings>,
+) -> Result<Vec<MessageId>, Error> {
+    let disabled = dashboard.is_none();
+    {
+        let mut settings = chat_resources.settings.write().await;
+        settings.dashboard = dashboard;
+    }
+    chat_resources.save_settings().await?;
+    if !disabled {
+        return Ok(Vec::new());
+    }
+    let messages = {
+        let mut results = chat_resources.results.write().await;
+        std::mem::take(&mut results.dashboard)
+    };
+    chat_resources.save_results().await?;
+    Ok(messages)
+}
+
+pub async fn dashboard_items(
+    chat: ChatId,
+) -> Result<Vec<(String, ChatRepoSettings, ChatRepoResults)>, Error> {
+    let mut items = Vec::new();
+    for repo in repos(chat).await? {
+        let resources = resources_chat_repo(chat, repo.clone()).await?;
+        let settings = resources.settings.read().await.clone();
+        let results = resources.results.read().await.clone();
+        items.push((repo, settings, results));
+    }
+    Ok(items)
+}
+
+// returns queued events not delivered yet
+pub async fn digest_disable(
+    chat_resources: &ChatResources,
+) -> Result<Option<(DateTime<Utc>, Vec<DigestEvent>)>, Error> {
+    {
+        let mut settings = chat_resources.settings.write().await;
+        settings.digest = None;
+    }
+    let pending = {
+        let mut results = chat_resources.results.write().await;
+        let since = results
+            .digest
+            .last_digest_at
+            .take()
+            .unwrap_or_else(Utc::now);
+        (since, std::mem::take(&mut results.digest.events))
+    };
+    chat_resources.save_settings().await?;
+    chat_resources.save_results().await?;
+    Ok(Some(pending).filter(|(_, events)| !events.is_empty()))
+}
+
+// returns `true` if the event is queued for the next digest
+pub async fn digest_queue(
+    chat_resources: &ChatResources,
+    urgent: bool,
+    event: DigestEvent,
+) -> Result<bool, Error> {
+    {
+        let settings = chat_resources.settings.read().await;
+        match &settings.digest {
+            None => return Ok(false),
+            Some(digest) if urgent && digest.urgent_bypass => return Ok(false),
+            Some(_) => (),
+        }
+    }
+    {
+        let mut results = chat_resources.results.write().await;
+        results.digest.events.push(event);
+    }
+    chat_resources.save_results().await?;
+    Ok(true)
+}
+
+// events are kept until `digest_clear`, so a failed digest is sent again in the next update
+pub async fn digest_due(
+    chat_resources: &ChatResources,
+) -> Result<Option<(DateTime<Utc>, Vec<DigestEvent>)>, Error> {
+    let schedule = {
+        let settings = chat_resources.settings.read().await;
+        match &settings.digest {
+            None => return Ok(None),
+            Some(digest) => Schedule::from_str(&digest.schedule)?,
+        }
+    };
+    let now = Utc::now();
+    let due = {
+        let mut results = chat_resources.results.write().await;
+        let since = *results.digest.last_digest_at.get_or_insert(now);
+        match schedule.after(&since).next() {
+            Some(due) if due <= now => {
+                // nothing to send, start the next period
+                if results.digest.events.is_empty() {
+                    results.digest.last_digest_at = Some(now);
+                    None
+                } else {
+                    Some((since, results.digest.events.clone()))
+                }
+            }
+            _ => None,
+        }
+    };
+    chat_resources.save_results().await?;
+    Ok(due)
+}
+
+// removes the first `count` events, those queued after `digest_due` are kept
+pub async fn digest_clear(chat_resources: &ChatResources, count: usize) -> Result<(), Error> {
+    {
+        let mut results = chat_resources.results.write().await;
+        let events = &mut results.digest.events;
+        events.drain(..count.min(events.len()));
+        results.digest.last_digest_at = Some(Utc::now());
+    }
+    chat_resources.save_results().await
+}
+
+pub async fn quiet_set(
+    chat_resources: &ChatResources,
+    quiet: Option<QuietSettings>,
+) -> Result<(), Error> {
+    {
+        let mut settings = chat_resources.settings.write().await;
+        settings.quiet = quiet;
+    }
+    chat_resources.save_settings().await
+}
+
+// returns the mode if now is in quiet hours
+pub async fn quiet_mode(chat_resources: &ChatResources) -> Option<QuietMode> {
+    let settings = chat_resources.settings.read().await;
+    settings
+        .quiet
+        .as_ref()
+        .filter(|quiet| quiet.contains(Utc::now()))
+        .map(|quiet| quiet.mode)
+}
+
+pub async fn living_message(resources: &ChatRepoResources, item: &Item) -> Option<MessageId> {
+    let results = resources.results.read().await;
+    match item {
+        Item::Commit(hash) => results.commits.get(hash).and_then(|r| r.message),
+        Item::Branch(branch) => results.branches.get(branch).and_then(|r| r.message),
+        Item::PRIssue(_) => None,
+    }
+}
+
+pub async fn living_message_set(
+    resources: &ChatRepoResources,
+    item: &Item,
+    message: MessageId,
+) -> Result<(), Error> {
+    {
+        let mut results = resources.results.write().await;
+        let slot = match item {
+            Item::Commit(hash) => results.commits.get_mut(hash).map(|r| &mut r.message),
+            Item::Branch(branch) => results.branches.get_mut(branch).map(|r| &mut r.message),
+            Item::PRIssue(_) => None,
+        };
+        match slot {
+            Some(slot) => *slot = Some(message),
+            // removed in this check
+            None => return Ok(()),
+        }
+    }
+    resources.save_results().await
+}
+
+const MAX_HISTORY_EVENTS: usize = 500;
+
+pub async fn history_push(
+    chat_resources: &ChatResources,
+    repo: Option<&str>,
+    kind: HistoryKind,
+    summary: String,
+) -> Result<(), Error> {
+    {
+        let mut history = chat_resources.history.write().await;
+        history.events.push_back(HistoryEvent {
+            time: Utc::now(),
+            repo: repo.map(str::to_string),
+            kind,
+            summary,
+        });
+        while history.events.len() > MAX_HISTORY_EVENTS {
+            history.events.pop_front();
+        }
+    }
+    chat_resources.save_history().await
+}
+
+// failing to record history should not fail the operation itself
+pub async fn history_record(resources: &ChatRepoResources, kind: HistoryKind, summary: String) {
+    let Task { chat, repo } = &resources.task;
+    history_record_chat(*chat, Some(repo), kind, summary).await
+}
+
+pub async fn history_record_chat(
+    chat: ChatId,
+    repo: Option<&str>,
+    kind: HistoryKind,
+    summary: String,
+) {
+    let result = match chat_resources(chat).await {
+        Ok(chat_resources) => history_push(&chat_resources, repo, kind, summary).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        log::error!("failed to record history ({chat}, {repo:?}): {e}");
+    }
+}
+
+// newest last
+pub async fn history(
+    chat_resources: &ChatResources,
+    repo: Option<&str>,
+    limit: usize,
+) -> Vec<HistoryEvent> {
+    let history = chat_resources.history.read().await;
+    let mut events: Vec<_> = history
+        .events
+        .iter()
+        .rev()
+        .filter(|e| repo.is_none() || e.repo.as_deref() == repo)
+        .take(limit)
+        .cloned()
+        .collect();
+    events.reverse();
+    events
+}
+
+pub async fn defer(
+    chat_resources: &ChatResources,
+    deferred: DeferredNotification,
+) -> Result<(), Error> {
+    {
+        let mut results = chat_resources.results.write().await;
+        results.deferred.push(deferred);
+    }
+    chat_resources.save_results().await
+}
+
+pub async fn deferred_take(
+    chat_resources: &ChatResources,
+) -> Result<Vec<DeferredNotification>, Error> {
+    let deferred = {
+        let mut results = chat_resources.results.write().await;
+        std::mem::take(&mut results.deferred)
+    };
+    if !deferred.is_empty() {
+        chat_resources.save_results().await?;
+    }
+    Ok(deferred)
+}
@@ -4,6 +4,27 @@ use teloxide::types::ChatId;
 
 use crate::{chat::Task, error::Error, options};
 
+#[derive(Debug, Clone)]
+pub struct ChatPaths {
+    pub settings: PathBuf,
+    pub results: PathBuf,
+    pub history: PathBuf,
+}
+
+impl ChatPaths {
+    pub fn new(chat: ChatId) -> Result<ChatPaths, Error> {
+        let outer = ChatRepoPaths::outer_dir(chat);
+        if !outer.is_dir() {
+            return Err(Error::NotInAllowList(chat));
+        }
+        Ok(Self {
+            settings: outer.join("settings.json"),
+            results: outer.join("results.json"),
+            history: outer.join("history.json"),
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ChatRepoPaths {
     // pub chat: PathBuf,
@@ -15,6 +36,10 @@ pub struct ChatRepoPaths {
 pub static GLOBAL_CHATS_OUTER: LazyLock<PathBuf> =
     LazyLock::new(|| options::get().working_dir.join("chats"));
 
+// data of denied chats, kept outside of `GLOBAL_CHATS_OUTER`
+pub static GLOBAL_CHATS_ARCHIVE: LazyLock<PathBuf> =
+    LazyLock::new(|| options::get().working_dir.join("archive").join("chats"));
+
 impl ChatRepoPaths {
     pub fn new(task: &Task) -> Result<ChatRepoPaths, Error> {
         let chat_path = Self::outer_dir(task.chat);
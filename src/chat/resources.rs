@@ -1,10 +1,16 @@
 use std::sync::LazyLock;
 
 use lockable::LockPool;
+use teloxide::types::ChatId;
 use tokio::{fs::create_dir_all, sync::RwLock};
 
 use crate::{
-    chat::{Task, paths::ChatRepoPaths, results::ChatRepoResults, settings::ChatRepoSettings},
+    chat::{
+        Task,
+        paths::{ChatPaths, ChatRepoPaths},
+        results::{ChatHistory, ChatRepoResults, ChatResults},
+        settings::{ChatRepoSettings, ChatSettings},
+    },
     error::Error,
     resources::{Resource, ResourcesMap},
     utils::{read_json, write_json},
@@ -13,6 +19,46 @@ use crate::{
 pub static RESOURCES_MAP: LazyLock<ResourcesMap<Task, ChatRepoResources>> =
     LazyLock::new(ResourcesMap::new);
 
+pub static CHAT_RESOURCES_MAP: LazyLock<ResourcesMap<ChatId, ChatResources>> =
+    LazyLock::new(ResourcesMap::new);
+
+pub struct ChatResources {
+    pub paths: ChatPaths,
+    pub settings: RwLock<ChatSettings>,
+    pub results: RwLock<ChatResults>,
+    pub history: RwLock<ChatHistory>,
+}
+
+impl Resource<ChatId> for ChatResources {
+    async fn open(chat: &ChatId) -> Result<Self, Error> {
+        let paths = ChatPaths::new(*chat)?;
+        let settings = RwLock::new(read_json(&paths.settings)?);
+        let results = RwLock::new(read_json(&paths.results)?);
+        let history = RwLock::new(read_json(&paths.history)?);
+        Ok(Self {
+            paths,
+            settings,
+            results,
+            history,
+        })
+    }
+}
+
+impl ChatResources {
+    pub async fn save_settings(&self) -> Result<(), Error> {
+        let in_mem = self.settings.read().await;
+        write_json(&self.paths.settings, &*in_mem)
+    }
+    pub async fn save_results(&self) -> Result<(), Error> {
+        let in_mem = self.results.read().await;
+        write_json(&self.paths.results, &*in_mem)
+    }
+    pub async fn save_history(&self) -> Result<(), Error> {
+        let in_mem = self.history.read().await;
+        write_json(&self.paths.history, &*in_mem)
+    }
+}
+
 pub struct ChatRepoResources {
     pub task: Task,
     pub paths: ChatRepoPaths,
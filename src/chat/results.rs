@@ -1,23 +1,178 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt,
+};
 
+use chrono::{DateTime, Utc};
+use git2::Commit;
 use serde::{Deserialize, Serialize};
+use teloxide::{types::MessageId, utils::markdown};
 
-use crate::condition::Action;
+use crate::{
+    chat::{
+        Item,
+        settings::{BranchSettings, CommitSettings, Delivery, NotifySettings, Subscriber},
+    },
+    condition::Action,
+};
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ChatResults {
+    #[serde(default)]
+    pub digest: DigestQueue,
+    // held during quiet hours
+    #[serde(default)]
+    pub deferred: Vec<DeferredNotification>,
+    // messages of the dashboard in order, split by length
+    #[serde(default)]
+    pub dashboard: Vec<MessageId>,
+    // hash of the dashboard without the update time, messages are only edited if it changes
+    #[serde(default)]
+    pub dashboard_hash: Option<u64>,
+}
+
+// oldest first, capped
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ChatHistory {
+    #[serde(default)]
+    pub events: VecDeque<HistoryEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEvent {
+    pub time: DateTime<Utc>,
+    // `None` for events of the whole chat
+    pub repo: Option<String>,
+    pub kind: HistoryKind,
+    // one line markdown
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistoryKind {
+    Sent,
+    Removed,
+    Expired,
+}
+
+impl fmt::Display for HistoryKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryKind::Sent => write!(f, "sent"),
+            HistoryKind::Removed => write!(f, "removed"),
+            HistoryKind::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeferredNotification {
+    pub repo: String,
+    pub notification: Notification,
+}
+
+// rendered notification, kept here as deferred ones are stored with the results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub item: Option<Item>,
+    // one line markdown, recorded instead of sending while muted
+    pub summary: String,
+    pub message: String,
+    pub subscribe_button: bool,
+    pub link_preview: bool,
+    pub private: Vec<(Subscriber, Delivery)>,
+    // notifications with subscribers may bypass the digest
+    pub urgent: bool,
+    // edit the previous notification of the item if any
+    #[serde(default)]
+    pub living: bool,
+    // subscribers mentioned in the chat, pinged separately when the living message is edited
+    #[serde(default)]
+    pub mentions: String,
+}
+
+impl Notification {
+    // `summary` is plain text, the first line of `message` may end inside an entity
+    pub fn new(item: Option<Item>, summary: &str, message: String) -> Self {
+        Self {
+            item,
+            summary: markdown::escape(summary),
+            message,
+            subscribe_button: false,
+            link_preview: true,
+            private: Vec::new(),
+            urgent: false,
+            living: false,
+            mentions: String::new(),
+        }
+    }
+
+    pub fn set_recipients(
+        &mut self,
+        notify: &NotifySettings,
+        private: Vec<(Subscriber, Delivery)>,
+    ) {
+        self.urgent = !notify.subscribers.is_empty() || !private.is_empty();
+        self.private = private;
+        self.mentions = notify
+            .subscribers
+            .iter()
+            .map(Subscriber::markdown)
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct DigestQueue {
+    #[serde(default)]
+    pub last_digest_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub events: Vec<DigestEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestEvent {
+    pub repo: String,
+    pub category: String,
+    // one line markdown
+    pub summary: String,
+}
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct ChatRepoResults {
     pub commits: BTreeMap<String, CommitResults>,
     pub branches: BTreeMap<String, BranchResults>,
+    #[serde(default)]
+    pub branch_pairs: BTreeMap<String, BranchPairResults>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CommitResults {
     pub branches: BTreeSet<String>,
+    // the living message, if enabled
+    #[serde(default)]
+    pub message: Option<MessageId>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct BranchResults {
     pub commit: Option<String>,
+    #[serde(default)]
+    pub changed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub stale_alerted_at: Option<DateTime<Utc>>,
+    // the living message, if enabled
+    #[serde(default)]
+    pub message: Option<MessageId>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct BranchPairResults {
+    // commits in the branch but not in the base
+    pub ahead: usize,
+    // commits in the base but not in the branch
+    pub behind: usize,
 }
 
 #[derive(Debug)]
@@ -25,6 +180,38 @@ pub struct CommitCheckResult {
     pub all: BTreeSet<String>,
     pub new: BTreeSet<String>,
     pub conditions: BTreeMap<String, Action>,
+    // `None` if the repository has no pipeline
+    pub pipeline: Option<PipelineProgress>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PipelineProgress {
+    pub stages: Vec<String>,
+    // number of stages up to the furthest one reached
+    pub reached: usize,
+    // estimated arrival times at the stages not reached yet
+    pub etas: Vec<(String, DateTime<Utc>)>,
+}
+
+impl PipelineProgress {
+    pub fn new(stages: &[String], branches: &BTreeSet<String>) -> Option<Self> {
+        if stages.is_empty() {
+            return None;
+        }
+        let reached = stages
+            .iter()
+            .rposition(|stage| branches.contains(stage))
+            .map_or(0, |i| i + 1);
+        Some(Self {
+            stages: stages.to_vec(),
+            reached,
+            etas: Vec::new(),
+        })
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.reached.checked_sub(1).map(|i| self.stages[i].as_str())
+    }
 }
 
 impl CommitCheckResult {
@@ -40,6 +227,118 @@ impl CommitCheckResult {
 pub struct BranchCheckResult {
     pub old: Option<String>,
     pub new: Option<String>,
+    pub change: BranchChange,
+    // new commits, only commits touching paths are included if filtered
+    pub commits: Vec<BranchCommit>,
+    // more commits are not listed
+    pub truncated: bool,
+    pub filtered: bool,
+    // no listed commit touches the paths, but the whole change does
+    pub unlisted_touching: bool,
+    pub diff_stat: Option<BranchDiffStat>,
+    pub stale: Option<BranchStale>,
+}
+
+impl BranchCheckResult {
+    pub fn should_notify(&self, settings: &BranchSettings) -> bool {
+        match self.change {
+            BranchChange::Unchanged => false,
+            BranchChange::ForcePush { .. } | BranchChange::Recreate | BranchChange::UnknownBase => {
+                true
+            }
+            _ if settings.force_push_only => false,
+            _ => !self.filtered || !self.commits.is_empty() || self.unlisted_touching,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BranchChange {
+    Unchanged,
+    Created,
+    Deleted,
+    FastForward,
+    ForcePush {
+        merge_base: String,
+        dropped: usize,
+        added: usize,
+    },
+    // the new commit shares no history with the old one
+    Recreate,
+    // the old commit is not available locally, e.g. pruned after a force-push
+    UnknownBase,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BranchStale {
+    // the branch has not moved since the time
+    Stale(DateTime<Utc>),
+    // the branch moves again after being stale since the time
+    MovingAgain(DateTime<Utc>),
+}
+
+#[derive(Debug, Clone)]
+pub struct BranchCommit {
+    pub id: String,
+    pub summary: String,
+    pub author: String,
+}
+
+impl BranchCommit {
+    pub fn from_git(commit: &Commit<'_>) -> Self {
+        Self {
+            id: commit.id().to_string(),
+            summary: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default())
+                .into_owned(),
+            author: String::from_utf8_lossy(commit.author().name_bytes()).into_owned(),
+        }
+    }
+}
+
+// file count only, line counts need blobs, which libgit2 can not fetch in a blobless clone
+#[derive(Debug, Clone, Copy)]
+pub struct BranchDiffStat {
+    pub files: usize,
+}
+
+#[derive(Debug)]
+pub struct BranchPairCheckResult {
+    pub new: BranchPairResults,
+    pub event: Option<BranchPairEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchPairEvent {
+    ThresholdCrossed(usize),
+    CaughtUp,
+}
+
+impl BranchPairEvent {
+    // a pair already over the threshold on the first check crosses it as well
+    pub fn between(
+        old_behind: Option<usize>,
+        behind: usize,
+        threshold: Option<usize>,
+    ) -> Option<Self> {
+        match (old_behind, threshold) {
+            (Some(old_behind), _) if old_behind != 0 && behind == 0 => {
+                Some(BranchPairEvent::CaughtUp)
+            }
+            (old_behind, Some(threshold))
+                if old_behind.unwrap_or(0) < threshold && behind >= threshold =>
+            {
+                Some(BranchPairEvent::ThresholdCrossed(threshold))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WatchCheckResult {
+    pub watch: String,
+    pub commit: String,
+    pub settings: CommitSettings,
 }
 
 #[derive(Debug)]
@@ -52,3 +351,82 @@ pub enum PRIssueCheckResult {
     Expired,
     Unknown,
 }
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branch_should_notify() {
+        let result = |commits: usize, truncated, unlisted_touching| BranchCheckResult {
+            old: Some("a".to_string()),
+            new: Some("b".to_string()),
+            change: BranchChange::FastForward,
+            commits: vec![
+                BranchCommit {
+                    id: "c".to_string(),
+                    summary: String::new(),
+                    author: String::new(),
+                };
+                commits
+            ],
+            truncated,
+            filtered: true,
+            unlisted_touching,
+            diff_stat: None,
+            stale: None,
+        };
+        let settings = BranchSettings::default();
+        assert!(result(1, false, false).should_notify(&settings));
+        assert!(!result(0, false, false).should_notify(&settings));
+        // commits past the listing limit are decided by the diff of the whole change
+        assert!(result(0, true, true).should_notify(&settings));
+        assert!(!result(0, true, false).should_notify(&settings));
+    }
+
+    #[test]
+    fn branch_pair_events() {
+        let event = |old, behind, threshold| BranchPairEvent::between(old, behind, threshold);
+        assert_eq!(
+            event(None, 20, Some(10)),
+            Some(BranchPairEvent::ThresholdCrossed(10))
+        );
+        assert_eq!(event(None, 5, Some(10)), None);
+        assert_eq!(
+            event(Some(5), 10, Some(10)),
+            Some(BranchPairEvent::ThresholdCrossed(10))
+        );
+        assert_eq!(event(Some(12), 20, Some(10)), None);
+        assert_eq!(event(Some(3), 0, None), Some(BranchPairEvent::CaughtUp));
+        assert_eq!(event(None, 0, Some(10)), None);
+    }
+
+    #[test]
+    fn pipeline_progress() {
+        let stages: Vec<String> = ["staging", "staging-next", "master"]
+            .map(String::from)
+            .to_vec();
+        let branches = |names: &[&str]| names.iter().map(|b| b.to_string()).collect();
+        assert!(PipelineProgress::new(&[], &branches(&["master"])).is_none());
+
+        let progress = PipelineProgress::new(&stages, &branches(&[])).unwrap();
+        assert_eq!(progress.reached, 0);
+        assert_eq!(progress.current(), None);
+
+        let progress = PipelineProgress::new(&stages, &branches(&["staging", "other"])).unwrap();
+        assert_eq!(progress.reached, 1);
+        assert_eq!(progress.current(), Some("staging"));
+
+        // the furthest stage counts even if earlier ones are missing
+        let progress = PipelineProgress::new(&stages, &branches(&["master"])).unwrap();
+        assert_eq!(progress.reached, 3);
+        assert_eq!(progress.current(), Some("master"));
+    }
+}
@@ -1,11 +1,181 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    time::Duration,
+};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use teloxide::{types::User, utils::markdown};
+use teloxide::{
+    types::{User, UserId},
+    utils::markdown,
+};
 use url::Url;
 
-use crate::error::Error;
+use crate::{
+    chat::Item,
+    error::Error,
+    i18n::{Key, Language},
+    template::TemplateKind,
+    utils::first_line,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ChatSettings {
+    #[serde(default)]
+    pub mute: Option<MuteSettings>,
+    #[serde(default)]
+    pub delivery: BTreeMap<UserId, Delivery>,
+    #[serde(default)]
+    pub digest: Option<DigestSettings>,
+    #[serde(default)]
+    pub quiet: Option<QuietSettings>,
+    #[serde(default)]
+    pub language: Language,
+    #[serde(default)]
+    pub templates: BTreeMap<TemplateKind, String>,
+    #[serde(default)]
+    pub dashboard: Option<DashboardSettings>,
+    #[serde(default)]
+    pub roles: BTreeMap<UserId, Role>,
+    // role of users without an explicit one
+    #[serde(default)]
+    pub default_role: Role,
+    #[serde(default)]
+    pub quota: QuotaSettings,
+}
+
+impl ChatSettings {
+    pub fn role(&self, user: UserId) -> Role {
+        self.roles.get(&user).copied().unwrap_or(self.default_role)
+    }
+}
+
+#[derive(
+    clap::ValueEnum,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    // may only read and subscribe, the default for members who are not chat administrators
+    #[default]
+    Viewer,
+    // may add, edit and remove tracked items
+    Editor,
+    // may also change chat settings and roles
+    Owner,
+}
+
+// overrides of the global limits, set by admins
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct QuotaSettings {
+    pub commits: Option<usize>,
+    pub pr_issues: Option<usize>,
+    pub branches: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaKind {
+    Commits,
+    PRIssues,
+    Branches,
+}
+
+impl fmt::Display for QuotaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaKind::Commits => write!(f, "commits"),
+            QuotaKind::PRIssues => write!(f, "PRs/issues"),
+            QuotaKind::Branches => write!(f, "branches"),
+        }
+    }
+}
+
+impl QuotaKind {
+    pub const ALL: [QuotaKind; 3] = [QuotaKind::Commits, QuotaKind::PRIssues, QuotaKind::Branches];
+
+    pub fn count(self, settings: &ChatRepoSettings) -> usize {
+        match self {
+            QuotaKind::Commits => settings.commits.len(),
+            QuotaKind::PRIssues => settings.pr_issues.len(),
+            QuotaKind::Branches => settings.branches.len(),
+        }
+    }
+}
+
+impl QuotaSettings {
+    pub fn get(&self, kind: QuotaKind) -> Option<usize> {
+        match kind {
+            QuotaKind::Commits => self.commits,
+            QuotaKind::PRIssues => self.pr_issues,
+            QuotaKind::Branches => self.branches,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardSettings {
+    pub pin: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietSettings {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub timezone: Tz,
+    pub mode: QuietMode,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuietMode {
+    // send with notifications disabled
+    #[default]
+    Silent,
+    // hold messages until quiet hours end
+    Defer,
+}
+
+impl QuietSettings {
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let time = now.with_timezone(&self.timezone).time();
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            // the window spans midnight
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestSettings {
+    // cron expression
+    pub schedule: String,
+    // notifications with subscribers are sent immediately
+    #[serde(default)]
+    pub urgent_bypass: bool,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Delivery {
+    // mention in the chat
+    #[default]
+    Mention,
+    // private message only, mention in the chat if the private message fails
+    Private,
+    Both,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ChatRepoSettings {
@@ -15,11 +185,35 @@ pub struct ChatRepoSettings {
     pub commits: BTreeMap<String, CommitSettings>,
     #[serde(default)]
     pub branches: BTreeMap<String, BranchSettings>,
+    #[serde(default)]
+    pub watches: BTreeMap<String, WatchSettings>,
+    #[serde(default)]
+    pub branch_pairs: BTreeMap<String, BranchPairSettings>,
+}
+
+impl ChatRepoSettings {
+    pub fn items(&self) -> Vec<Item> {
+        let commits = self.commits.keys().cloned().map(Item::Commit);
+        let branches = self.branches.keys().cloned().map(Item::Branch);
+        let pr_issues = self.pr_issues.keys().cloned().map(Item::PRIssue);
+        commits.chain(branches).chain(pr_issues).collect()
+    }
+
+    pub fn notify_mut(&mut self, item: &Item) -> Option<&mut NotifySettings> {
+        match item {
+            Item::Commit(hash) => self.commits.get_mut(hash).map(|s| &mut s.notify),
+            Item::Branch(branch) => self.branches.get_mut(branch).map(|s| &mut s.notify),
+            Item::PRIssue(id) => self.pr_issues.get_mut(id).map(|s| &mut s.notify),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommitSettings {
     pub url: Option<Url>,
+    // edit the first notification instead of sending new ones
+    #[serde(default)]
+    pub living: bool,
     #[serde(flatten)]
     pub notify: NotifySettings,
 }
@@ -34,6 +228,48 @@ pub struct PRIssueSettings {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct BranchSettings {
+    #[serde(default)]
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub diff_stat: bool,
+    #[serde(default)]
+    pub force_push_only: bool,
+    #[serde(default)]
+    pub stale: Option<StaleSettings>,
+    // edit the first notification instead of sending new ones
+    #[serde(default)]
+    pub living: bool,
+    #[serde(flatten)]
+    pub notify: NotifySettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleSettings {
+    pub after: Duration,
+    #[serde(default)]
+    pub repeat: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchPairSettings {
+    pub branch: String,
+    pub base: String,
+    #[serde(default)]
+    pub threshold: Option<usize>,
+    #[serde(flatten)]
+    pub notify: NotifySettings,
+}
+
+impl BranchPairSettings {
+    pub fn key(branch: &str, base: &str) -> String {
+        format!("{branch}...{base}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchSettings {
+    #[serde(with = "serde_regex")]
+    pub message_regex: Regex,
     #[serde(flatten)]
     pub notify: NotifySettings,
 }
@@ -44,16 +280,34 @@ pub struct NotifySettings {
     pub comment: String,
     #[serde(default)]
     pub subscribers: BTreeSet<Subscriber>,
+    #[serde(default)]
+    pub mute: Option<MuteSettings>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MuteSettings {
+    // muted until unmuted manually if `None`
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    // summaries of notifications suppressed while muted
+    #[serde(default)]
+    pub suppressed: Vec<String>,
+}
+
+impl MuteSettings {
+    pub fn expired(&self, now: DateTime<Utc>) -> bool {
+        self.until.is_some_and(|until| until <= now)
+    }
 }
 
 impl NotifySettings {
-    pub fn subscribers_markdown(&self) -> String {
+    pub fn subscribers_markdown(&self, lang: Language) -> String {
         let mut result = String::new();
         if !self.subscribers.is_empty() {
             if !result.is_empty() {
                 result.push_str("\n\n");
             }
-            result.push_str("*subscribers*: ");
+            result.push_str(lang.text(Key::Subscribers));
             result.push_str(
                 &self
                     .subscribers
@@ -67,14 +321,18 @@ impl NotifySettings {
     }
 
     pub fn description_markdown(&self) -> String {
-        markdown::escape(self.comment.trim().lines().next().unwrap_or_default())
+        markdown::escape(first_line(&self.comment))
     }
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Serialize, Deserialize)]
 #[serde(try_from = "SubscriberCompat")]
 pub enum Subscriber {
-    Telegram { markdown_mention: String },
+    Telegram {
+        markdown_mention: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        user_id: Option<UserId>,
+    },
 }
 
 impl TryFrom<SubscriberCompat> for Subscriber {
@@ -85,12 +343,15 @@ impl TryFrom<SubscriberCompat> for Subscriber {
             SubscriberCompat::Telegram {
                 markdown_mention,
                 username,
+                user_id,
             } => match (markdown_mention, username) {
                 (Some(mention), _) => Ok(Subscriber::Telegram {
                     markdown_mention: mention.clone(),
+                    user_id: *user_id,
                 }),
                 (_, Some(username)) => Ok(Subscriber::Telegram {
                     markdown_mention: format!("@{}", markdown::escape(username)),
+                    user_id: *user_id,
                 }),
                 (_, _) => Err(Error::InvalidSubscriber(compat)),
             },
@@ -103,6 +364,8 @@ pub enum SubscriberCompat {
     Telegram {
         markdown_mention: Option<String>,
         username: Option<String>, // field for compatibility
+        #[serde(default)]
+        user_id: Option<UserId>,
     },
 }
 
@@ -115,12 +378,47 @@ impl Subscriber {
         };
         Self::Telegram {
             markdown_mention: mention,
+            user_id: Some(user.id),
         }
     }
 
     pub fn markdown(&self) -> &str {
         match self {
-            Subscriber::Telegram { markdown_mention } => markdown_mention,
+            Subscriber::Telegram {
+                markdown_mention, ..
+            } => markdown_mention,
+        }
+    }
+
+    pub fn user_id(&self) -> Option<UserId> {
+        match self {
+            Subscriber::Telegram { user_id, .. } => *user_id,
+        }
+    }
+
+    // the text of the mention without markup, links are not part of it
+    pub fn display_name(&self) -> String {
+        let mention = self.markdown();
+        let text = match mention.strip_prefix('[').and_then(|s| s.rsplit_once("](")) {
+            Some((text, _url)) => text,
+            None => mention,
+        };
+        let mut result = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => result.extend(chars.next()),
+                c => result.push(c),
+            }
+        }
+        result
+    }
+
+    // subscribers saved before user ids were recorded can only be matched by mention
+    pub fn same_user(&self, other: &Subscriber) -> bool {
+        match (self.user_id(), other.user_id()) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.markdown() == other.markdown(),
         }
     }
 }
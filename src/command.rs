@@ -1,9 +1,22 @@
+use crate::chat::Item;
+use crate::chat::settings::{
+    BranchSettings, CommitSettings, Delivery, NotifySettings, QuietMode, QuotaSettings, Role,
+    StaleSettings,
+};
 use crate::condition;
 use crate::error::Error;
 use crate::github::GitHubInfo;
+use crate::i18n::Language;
+use crate::repo::settings::RepoSettings;
+use crate::template::TemplateKind;
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use clap::Args;
 use clap::ColorChoice;
 use clap::Parser;
+use regex::Regex;
 use std::{ffi::OsString, iter};
+use url::Url;
 
 const HELP_TEMPLATE: &str = "\
 {before-help}{name} {version}
@@ -29,17 +42,37 @@ const HELP_TEMPLATE: &str = "\
 pub enum Notifier {
     #[command(about = "return current chat id")]
     ChatId,
+    #[command(about = "allow a chat to use the bot (admin only)")]
+    ChatAllow {
+        #[arg(allow_negative_numbers = true)]
+        chat: i64,
+    },
+    #[command(about = "remove a chat from the allow list (admin only)")]
+    ChatDeny {
+        #[arg(allow_negative_numbers = true)]
+        chat: i64,
+        // what to do with data of the chat
+        #[arg(long, value_enum, default_value_t)]
+        data: DenyData,
+    },
+    #[command(about = "list allowed chats (admin only)")]
+    ChatList,
+    #[command(about = "show or override limits of tracked items of a chat (admin only)")]
+    ChatQuota {
+        #[arg(allow_negative_numbers = true)]
+        chat: i64,
+        #[command(flatten)]
+        options: QuotaEditOptions,
+    },
+    #[command(about = "request access to the bot from the admin")]
+    AccessRequest,
     #[command(about = "add a repository")]
     RepoAdd { name: String, url: String },
     #[command(about = "edit settings of a repository")]
     RepoEdit {
         name: String,
-        #[arg(long, short)]
-        branch_regex: Option<String>,
-        #[arg(long, short, value_parser = GitHubInfo::parse, group = "edit_github_info")]
-        github_info: Option<GitHubInfo>,
-        #[arg(long, group = "edit_github_info")]
-        clear_github_info: bool,
+        #[command(flatten)]
+        options: RepoEditOptions,
     },
     #[command(about = "remove a repository")]
     RepoRemove { name: String },
@@ -49,9 +82,21 @@ pub enum Notifier {
         hash: String,
         #[arg(long, short)]
         comment: String,
+        // edit the first notification instead of sending new ones
+        #[arg(long)]
+        living: bool,
     },
     #[command(about = "remove a commit")]
     CommitRemove { repo: String, hash: String },
+    #[command(about = "edit settings of a commit")]
+    CommitEdit {
+        repo: String,
+        hash: String,
+        #[command(flatten)]
+        notify: NotifyEdit,
+        #[command(flatten)]
+        options: CommitEditOptions,
+    },
     #[command(about = "fire a commit check immediately")]
     CommitCheck { repo: String, hash: String },
     #[command(about = "subscribe to a commit")]
@@ -73,6 +118,18 @@ pub enum Notifier {
         repo_or_url: String,
         id: Option<u64>,
     },
+    #[command(
+        visible_alias("issue-edit"),
+        about = "edit settings of a pull request/issue"
+    )]
+    PrEdit {
+        repo_or_url: String,
+        id: Option<u64>,
+        #[command(flatten)]
+        notify: NotifyEdit,
+        #[arg(long, short)]
+        url: Option<Url>,
+    },
     #[command(visible_alias("issue-check"), about = "check a pull request/issue")]
     PrCheck {
         repo_or_url: String,
@@ -89,9 +146,23 @@ pub enum Notifier {
         unsubscribe: bool,
     },
     #[command(about = "add a branch")]
-    BranchAdd { repo: String, branch: String },
+    BranchAdd {
+        repo: String,
+        branch: String,
+        #[command(flatten)]
+        options: BranchOptions,
+    },
     #[command(about = "remove a branch")]
     BranchRemove { repo: String, branch: String },
+    #[command(about = "edit settings of a branch")]
+    BranchEdit {
+        repo: String,
+        branch: String,
+        #[command(flatten)]
+        notify: NotifyEdit,
+        #[command(flatten)]
+        options: BranchEditOptions,
+    },
     #[command(about = "fire a branch check immediately")]
     BranchCheck { repo: String, branch: String },
     #[command(about = "subscribe to a branch")]
@@ -101,6 +172,142 @@ pub enum Notifier {
         #[arg(short, long)]
         unsubscribe: bool,
     },
+    #[command(about = "add a branch pair reporting how far a branch lags its base")]
+    BranchPairAdd {
+        repo: String,
+        branch: String,
+        base: String,
+        #[arg(long, short)]
+        threshold: Option<usize>,
+    },
+    #[command(about = "remove a branch pair")]
+    BranchPairRemove {
+        repo: String,
+        branch: String,
+        base: String,
+    },
+    #[command(about = "compare two branches immediately")]
+    BranchCompare {
+        repo: String,
+        branch: String,
+        base: String,
+    },
+    #[command(about = "add a watch rule tracking commits with matching messages")]
+    WatchAdd {
+        repo: String,
+        identifier: String,
+        #[arg(long, short)]
+        regex: String,
+        #[arg(long, short)]
+        comment: Option<String>,
+    },
+    #[command(about = "remove a watch rule")]
+    WatchRemove { repo: String, identifier: String },
+    #[command(about = "mute a commit/branch/PR/issue, or the whole chat")]
+    Mute {
+        #[command(flatten)]
+        target: ItemTarget,
+        #[arg(long = "for")]
+        duration: Option<humantime::Duration>,
+    },
+    #[command(about = "unmute a commit/branch/PR/issue, or the whole chat")]
+    Unmute {
+        #[command(flatten)]
+        target: ItemTarget,
+    },
+    #[command(about = "show or set how subscribed notifications are delivered to you")]
+    Delivery {
+        #[arg(value_enum)]
+        delivery: Option<Delivery>,
+    },
+    #[command(about = "show or set digest mode of the chat")]
+    Digest {
+        // cron expression, e.g. "0 0 9 * * *"
+        #[arg(long, short, group = "edit_digest")]
+        schedule: Option<String>,
+        #[arg(long, short, requires = "schedule")]
+        urgent_bypass: bool,
+        #[arg(long, group = "edit_digest")]
+        disable: bool,
+    },
+    #[command(about = "show or set quiet hours of the chat")]
+    Quiet {
+        #[arg(long = "from", requires_all = ["end", "timezone"], group = "edit_quiet")]
+        start: Option<NaiveTime>,
+        #[arg(long = "to", requires = "start")]
+        end: Option<NaiveTime>,
+        #[arg(long, short, requires = "start")]
+        timezone: Option<Tz>,
+        #[arg(long, short, value_enum, default_value_t, requires = "start")]
+        mode: QuietMode,
+        #[arg(long, group = "edit_quiet")]
+        disable: bool,
+    },
+    #[command(about = "show or set language of the chat")]
+    Language {
+        #[arg(value_enum)]
+        language: Option<Language>,
+    },
+    #[command(
+        about = "show or set message template of an event kind",
+        long_about = "show or set message template of an event kind, \
+                      placeholders are written as `{name}`, use `{{` and `}}` for literal braces"
+    )]
+    Template {
+        #[arg(value_enum)]
+        kind: TemplateKind,
+        #[arg(group = "edit_template")]
+        template: Option<String>,
+        // edit the default template of the repository instead (admin only)
+        #[arg(long, short)]
+        repo: Option<String>,
+        #[arg(long, group = "edit_template")]
+        clear: bool,
+    },
+    #[command(about = "show sent notifications and automatic removals of the chat")]
+    History {
+        repo: Option<String>,
+        #[arg(long, short, default_value_t = 20, value_parser = clap::value_parser!(u16).range(1..=100))]
+        limit: u16,
+    },
+    #[command(about = "show or set the status board of all tracked items in the chat")]
+    Dashboard {
+        #[arg(long, group = "edit_dashboard")]
+        enable: bool,
+        // pin the first message of the dashboard
+        #[arg(long, short, requires = "enable")]
+        pin: bool,
+        #[arg(long, group = "edit_dashboard")]
+        disable: bool,
+    },
+    #[command(
+        about = "set the role of a user in the chat",
+        long_about = "set the role of a user in the chat, reply to a message of the user \
+                      or give the user id, chat administrators are always owners"
+    )]
+    RoleSet {
+        // clear the explicit role if not given
+        #[arg(value_enum)]
+        role: Option<Role>,
+        #[arg(long, short, group = "role_target")]
+        user: Option<u64>,
+        // set the role of users without an explicit one
+        #[arg(long, group = "role_target", requires = "role")]
+        default: bool,
+    },
+    #[command(about = "list roles of the chat")]
+    RoleList,
+    #[command(about = "export tracking settings of the chat as a file")]
+    Export { repo: Option<String> },
+    #[command(
+        about = "import tracking settings from an exported file",
+        long_about = "import tracking settings, send it as the caption of an exported file or reply to a message containing one"
+    )]
+    Import {
+        // replace settings of the repositories in the file instead of merging
+        #[arg(long)]
+        replace: bool,
+    },
     #[command(about = "add an auto clean condition")]
     ConditionAdd {
         repo: String,
@@ -112,8 +319,355 @@ pub enum Notifier {
     },
     #[command(about = "remove an auto clean condition")]
     ConditionRemove { repo: String, identifier: String },
-    #[command(about = "list repositories and commits")]
-    List,
+    #[command(about = "list repositories and tracked items")]
+    List {
+        #[command(flatten)]
+        filter: ListFilter,
+        // show the subscribers of each item
+        #[arg(long)]
+        subscribers: bool,
+        // send the list as a text file instead of pages
+        #[arg(long)]
+        file: bool,
+    },
+}
+
+impl Notifier {
+    pub fn required_role(&self) -> Role {
+        match self {
+            Notifier::RoleSet { .. } | Notifier::Import { .. } => Role::Owner,
+            Notifier::Digest {
+                schedule, disable, ..
+            } if schedule.is_some() || *disable => Role::Owner,
+            Notifier::Quiet { start, disable, .. } if start.is_some() || *disable => Role::Owner,
+            Notifier::Language { language } if language.is_some() => Role::Owner,
+            Notifier::Template {
+                template, clear, ..
+            } if template.is_some() || *clear => Role::Owner,
+            Notifier::Dashboard {
+                enable, disable, ..
+            } if *enable || *disable => Role::Owner,
+            // checking, subscribing and personal settings
+            Notifier::ChatId
+            | Notifier::AccessRequest
+            | Notifier::CommitCheck { .. }
+            | Notifier::CommitSubscribe { .. }
+            | Notifier::PrCheck { .. }
+            | Notifier::PrSubscribe { .. }
+            | Notifier::BranchCheck { .. }
+            | Notifier::BranchSubscribe { .. }
+            | Notifier::BranchCompare { .. }
+            | Notifier::Delivery { .. }
+            | Notifier::Digest { .. }
+            | Notifier::Quiet { .. }
+            | Notifier::Language { .. }
+            | Notifier::Template { .. }
+            | Notifier::History { .. }
+            | Notifier::Dashboard { .. }
+            | Notifier::RoleList
+            | Notifier::Export { .. }
+            | Notifier::List { .. } => Role::Viewer,
+            // adding, editing and removing tracked items, new commands need to opt out explicitly
+            _ => Role::Editor,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct BranchOptions {
+    #[arg(long = "path", short)]
+    pub paths: Vec<String>,
+    // number of changed files, line counts are not available
+    #[arg(long, short)]
+    pub diff_stat: bool,
+    #[arg(long, short)]
+    pub force_push_only: bool,
+    #[arg(long, short = 's')]
+    pub stale_after: Option<humantime::Duration>,
+    #[arg(long, requires = "stale_after")]
+    pub stale_repeat: Option<humantime::Duration>,
+    // edit the first notification instead of sending new ones
+    #[arg(long)]
+    pub living: bool,
+}
+
+impl From<BranchOptions> for BranchSettings {
+    fn from(options: BranchOptions) -> Self {
+        BranchSettings {
+            paths: options.paths,
+            diff_stat: options.diff_stat,
+            force_push_only: options.force_push_only,
+            stale: options.stale_after.map(|after| StaleSettings {
+                after: after.into(),
+                repeat: options.stale_repeat.map(Into::into),
+            }),
+            living: options.living,
+            notify: Default::default(),
+        }
+    }
+}
+
+// the whole chat if no repository is given
+#[derive(Debug, Args)]
+pub struct ItemTarget {
+    #[arg(requires = "kind")]
+    pub repo: Option<String>,
+    #[arg(value_enum, requires = "id")]
+    pub kind: Option<ItemKind>,
+    pub id: Option<String>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, Copy)]
+pub enum ItemKind {
+    Commit,
+    Branch,
+    #[value(alias = "issue")]
+    Pr,
+}
+
+impl ItemTarget {
+    pub fn item(self) -> Result<Option<(String, Item)>, Error> {
+        match (self.repo, self.kind, self.id) {
+            (Some(repo), Some(kind), Some(id)) => {
+                let item = match kind {
+                    ItemKind::Commit => Item::Commit(id),
+                    ItemKind::Branch => Item::Branch(id),
+                    ItemKind::Pr => Item::PRIssue(id.parse()?),
+                };
+                Ok(Some((repo, item)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ListFilter {
+    pub repo: Option<String>,
+    #[arg(long, short, value_enum)]
+    pub kind: Option<ListKind>,
+    // "me", a user id or a part of the name
+    #[arg(long, short)]
+    pub subscriber: Option<String>,
+    // case-insensitive text in ids and comments
+    #[arg(long, short = 'q')]
+    pub search: Option<String>,
+}
+
+impl ListFilter {
+    pub fn is_empty(&self) -> bool {
+        self.repo.is_none()
+            && self.kind.is_none()
+            && self.subscriber.is_none()
+            && self.search.is_none()
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ListKind {
+    Commit,
+    #[value(alias = "issue")]
+    Pr,
+    Branch,
+    BranchPair,
+    Watch,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DenyData {
+    // move to the archive directory
+    #[default]
+    Archive,
+    Delete,
+}
+
+// unspecified limits are left unchanged
+#[derive(Debug, Args)]
+pub struct QuotaEditOptions {
+    #[arg(long)]
+    pub commits: Option<usize>,
+    #[arg(long)]
+    pub pr_issues: Option<usize>,
+    #[arg(long)]
+    pub branches: Option<usize>,
+    // use the global limits again
+    #[arg(long, conflicts_with_all = ["commits", "pr_issues", "branches"])]
+    pub reset: bool,
+}
+
+impl QuotaEditOptions {
+    pub fn is_edit(&self) -> bool {
+        self.commits.is_some() || self.pr_issues.is_some() || self.branches.is_some() || self.reset
+    }
+
+    pub fn apply(self, quota: &mut QuotaSettings) {
+        if self.reset {
+            *quota = QuotaSettings::default();
+        }
+        if let Some(commits) = self.commits {
+            quota.commits = Some(commits);
+        }
+        if let Some(pr_issues) = self.pr_issues {
+            quota.pr_issues = Some(pr_issues);
+        }
+        if let Some(branches) = self.branches {
+            quota.branches = Some(branches);
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct NotifyEdit {
+    #[arg(long, short)]
+    pub comment: Option<String>,
+    #[arg(long)]
+    pub clear_subscribers: bool,
+}
+
+impl NotifyEdit {
+    pub fn apply(self, notify: &mut NotifySettings) {
+        if let Some(comment) = self.comment {
+            notify.comment = comment;
+        }
+        if self.clear_subscribers {
+            notify.subscribers.clear();
+        }
+    }
+}
+
+// unspecified options are left unchanged
+#[derive(Debug, Args)]
+pub struct RepoEditOptions {
+    #[arg(long, short)]
+    pub branch_regex: Option<String>,
+    #[arg(long, short, value_parser = GitHubInfo::parse, group = "edit_github_info")]
+    pub github_info: Option<GitHubInfo>,
+    #[arg(long, group = "edit_github_info")]
+    pub clear_github_info: bool,
+    // ordered branch stages, e.g. "staging,staging-next,master"
+    #[arg(long, short, value_delimiter = ',', group = "edit_pipeline")]
+    pub pipeline: Vec<String>,
+    #[arg(long, group = "edit_pipeline")]
+    pub clear_pipeline: bool,
+}
+
+impl RepoEditOptions {
+    // settings are left unchanged on errors
+    pub fn apply(self, settings: &mut RepoSettings) -> Result<(), Error> {
+        let mut edited = settings.clone();
+        if let Some(r) = self.branch_regex {
+            edited.branch_regex = Regex::new(&format!("^({r})$"))?;
+        }
+        if let Some(info) = self.github_info {
+            edited.github_info = Some(info);
+        }
+        if self.clear_github_info {
+            edited.github_info = None;
+        }
+        if !self.pipeline.is_empty() {
+            edited.pipeline = self.pipeline;
+        }
+        if self.clear_pipeline {
+            edited.pipeline.clear();
+        }
+        // stages are only known if their branches are watched
+        if let Some(stage) = edited
+            .pipeline
+            .iter()
+            .find(|stage| !edited.branch_regex.is_match(stage))
+        {
+            return Err(Error::PipelineStageNotWatched(stage.clone()));
+        }
+        // existing reach-stage conditions would never fire otherwise
+        for (identifier, c) in &edited.conditions {
+            if let condition::GeneralCondition::ReachStage(condition) = &c.condition
+                && condition.stage.get() > edited.pipeline.len()
+            {
+                return Err(Error::ConditionStageOutOfPipeline {
+                    identifier: identifier.clone(),
+                    stage: condition.stage.get(),
+                    stages: edited.pipeline.len(),
+                });
+            }
+        }
+        *settings = edited;
+        Ok(())
+    }
+}
+
+// unspecified options are left unchanged
+#[derive(Debug, Args)]
+pub struct CommitEditOptions {
+    #[arg(long, short, group = "edit_url")]
+    pub url: Option<Url>,
+    #[arg(long, group = "edit_url")]
+    pub clear_url: bool,
+    #[arg(long)]
+    pub living: Option<bool>,
+}
+
+impl CommitEditOptions {
+    pub fn apply(self, settings: &mut CommitSettings) {
+        if let Some(url) = self.url {
+            settings.url = Some(url);
+        }
+        if self.clear_url {
+            settings.url = None;
+        }
+        if let Some(living) = self.living {
+            settings.living = living;
+        }
+    }
+}
+
+// unspecified options are left unchanged
+#[derive(Debug, Args)]
+pub struct BranchEditOptions {
+    #[arg(long = "path", short, group = "edit_paths")]
+    pub paths: Vec<String>,
+    #[arg(long, group = "edit_paths")]
+    pub clear_paths: bool,
+    #[arg(long, short)]
+    pub diff_stat: Option<bool>,
+    #[arg(long, short)]
+    pub force_push_only: Option<bool>,
+    #[arg(long, short = 's', group = "edit_stale")]
+    pub stale_after: Option<humantime::Duration>,
+    #[arg(long, requires = "stale_after")]
+    pub stale_repeat: Option<humantime::Duration>,
+    #[arg(long, group = "edit_stale")]
+    pub clear_stale: bool,
+    #[arg(long)]
+    pub living: Option<bool>,
+}
+
+impl BranchEditOptions {
+    pub fn apply(self, settings: &mut BranchSettings) {
+        if !self.paths.is_empty() {
+            settings.paths = self.paths;
+        }
+        if self.clear_paths {
+            settings.paths.clear();
+        }
+        if let Some(diff_stat) = self.diff_stat {
+            settings.diff_stat = diff_stat;
+        }
+        if let Some(force_push_only) = self.force_push_only {
+            settings.force_push_only = force_push_only;
+        }
+        if let Some(after) = self.stale_after {
+            settings.stale = Some(StaleSettings {
+                after: after.into(),
+                repeat: self.stale_repeat.map(Into::into),
+            });
+        }
+        if self.clear_stale {
+            settings.stale = None;
+        }
+        if let Some(living) = self.living {
+            settings.living = living;
+        }
+    }
 }
 
 pub fn parse(raw_input: String) -> Result<Notifier, Error> {
@@ -225,6 +779,135 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_mute() {
+        let command = parse("mute repo pr 42 --for 2d".to_owned()).unwrap();
+        let Notifier::Mute { target, duration } = command else {
+            panic!("unexpected command: {command:?}");
+        };
+        assert_eq!(
+            target.item().unwrap(),
+            Some(("repo".to_owned(), Item::PRIssue(42)))
+        );
+        assert_eq!(
+            duration.map(Into::into),
+            Some(std::time::Duration::from_secs(2 * 24 * 60 * 60))
+        );
+        let command = parse("unmute".to_owned()).unwrap();
+        let Notifier::Unmute { target } = command else {
+            panic!("unexpected command: {command:?}");
+        };
+        assert_eq!(target.item().unwrap(), None);
+    }
+
+    #[test]
+    fn parse_repo_edit_pipeline() {
+        let command = parse(
+            "repo-edit nixpkgs --branch-regex staging.*|master --pipeline staging,staging-next,master"
+                .to_owned(),
+        )
+        .unwrap();
+        let Notifier::RepoEdit { name, options } = command else {
+            panic!("unexpected command: {command:?}");
+        };
+        assert_eq!(name, "nixpkgs");
+        let mut settings = RepoSettings::default();
+        options.apply(&mut settings).unwrap();
+        assert_eq!(settings.pipeline, vec!["staging", "staging-next", "master"]);
+
+        // stages must match the branch regex
+        let command = parse("repo-edit nixpkgs --pipeline staging,unstable".to_owned()).unwrap();
+        let Notifier::RepoEdit { options, .. } = command else {
+            panic!("unexpected command: {command:?}");
+        };
+        assert!(matches!(
+            options.apply(&mut settings),
+            Err(Error::PipelineStageNotWatched(stage)) if stage == "unstable"
+        ));
+        assert_eq!(settings.pipeline, vec!["staging", "staging-next", "master"]);
+
+        // reach-stage conditions must stay within the pipeline
+        let condition = condition::GeneralCondition::parse(
+            condition::Kind::RemoveIfReachStage,
+            "3",
+            &settings.pipeline,
+        )
+        .unwrap();
+        settings.conditions.insert(
+            "merged".to_string(),
+            crate::repo::settings::ConditionSettings { condition },
+        );
+        for edit in [
+            "repo-edit nixpkgs --pipeline staging,master",
+            "repo-edit nixpkgs --clear-pipeline",
+        ] {
+            let Notifier::RepoEdit { options, .. } = parse(edit.to_owned()).unwrap() else {
+                panic!("unexpected command: {edit}");
+            };
+            assert!(matches!(
+                options.apply(&mut settings),
+                Err(Error::ConditionStageOutOfPipeline { identifier, stage: 3, .. })
+                    if identifier == "merged"
+            ));
+        }
+        assert_eq!(settings.pipeline, vec!["staging", "staging-next", "master"]);
+    }
+
+    #[test]
+    fn parse_negative_chat_id() {
+        let command = parse("chat-deny -100123 --data delete".to_owned()).unwrap();
+        let Notifier::ChatDeny { chat, data } = command else {
+            panic!("unexpected command: {command:?}");
+        };
+        assert_eq!(chat, -100123);
+        assert_eq!(data, DenyData::Delete);
+    }
+
+    #[test]
+    fn parse_chat_quota() {
+        let command = parse("chat-quota -100123 --commits 10 --branches 0".to_owned()).unwrap();
+        let Notifier::ChatQuota { chat, options } = command else {
+            panic!("unexpected command: {command:?}");
+        };
+        assert_eq!(chat, -100123);
+        let mut quota = QuotaSettings {
+            pr_issues: Some(5),
+            ..Default::default()
+        };
+        options.apply(&mut quota);
+        assert_eq!(
+            (quota.commits, quota.pr_issues, quota.branches),
+            (Some(10), Some(5), Some(0))
+        );
+        assert!(parse("chat-quota 1 --commits 10 --reset".to_owned()).is_err());
+    }
+
+    #[test]
+    fn required_roles() {
+        let role = |input: &str| parse(input.to_owned()).unwrap().required_role();
+        assert_eq!(role("commit-remove repo abc"), Role::Editor);
+        assert_eq!(role("commit-check repo abc"), Role::Viewer);
+        assert_eq!(role("language"), Role::Viewer);
+        assert_eq!(role("language zh-cn"), Role::Owner);
+        assert_eq!(role("role-set viewer --user 42"), Role::Owner);
+        assert_eq!(role("mute repo pr 42"), Role::Editor);
+        assert_eq!(role("list"), Role::Viewer);
+        assert_eq!(Role::default(), Role::Viewer);
+    }
+
+    #[test]
+    fn parse_list_filter() {
+        let command = parse("list nixpkgs -k issue -s me -q \"foo bar\"".to_owned()).unwrap();
+        let Notifier::List { filter, file, .. } = command else {
+            panic!("unexpected command: {command:?}");
+        };
+        assert_eq!(filter.repo.as_deref(), Some("nixpkgs"));
+        assert_eq!(filter.kind, Some(ListKind::Pr));
+        assert_eq!(filter.subscriber.as_deref(), Some("me"));
+        assert_eq!(filter.search.as_deref(), Some("foo bar"));
+        assert!(!file);
+    }
+
     #[test]
     fn verify_cli() {
         use clap::CommandFactory;
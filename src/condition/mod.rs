@@ -1,10 +1,12 @@
 pub mod in_branch;
+pub mod reach_stage;
 pub mod suppress_from_to;
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    chat::results::CommitCheckResult, condition::suppress_from_to::SuppressFromToCondition,
+    chat::results::CommitCheckResult,
+    condition::{reach_stage::ReachStageCondition, suppress_from_to::SuppressFromToCondition},
     error::Error,
 };
 
@@ -18,6 +20,7 @@ pub trait Condition {
 pub enum Kind {
     RemoveIfInBranch,
     SuppressFromTo,
+    RemoveIfReachStage,
 }
 
 #[derive(
@@ -39,10 +42,12 @@ impl Action {
 pub enum GeneralCondition {
     InBranch(InBranchCondition),
     SuppressFromTo(SuppressFromToCondition),
+    ReachStage(ReachStageCondition),
 }
 
 impl GeneralCondition {
-    pub fn parse(kind: Kind, expr: &str) -> Result<GeneralCondition, Error> {
+    // `pipeline` of the repository the condition is added to
+    pub fn parse(kind: Kind, expr: &str, pipeline: &[String]) -> Result<GeneralCondition, Error> {
         match kind {
             Kind::RemoveIfInBranch => {
                 Ok(GeneralCondition::InBranch(InBranchCondition::parse(expr)?))
@@ -50,6 +55,9 @@ impl GeneralCondition {
             Kind::SuppressFromTo => Ok(GeneralCondition::SuppressFromTo(
                 SuppressFromToCondition::parse(expr)?,
             )),
+            Kind::RemoveIfReachStage => Ok(GeneralCondition::ReachStage(
+                ReachStageCondition::parse(expr, pipeline)?,
+            )),
         }
     }
 }
@@ -59,6 +67,7 @@ impl Condition for GeneralCondition {
         match self {
             GeneralCondition::InBranch(c) => c.check(check_results),
             GeneralCondition::SuppressFromTo(c) => c.check(check_results),
+            GeneralCondition::ReachStage(c) => c.check(check_results),
         }
     }
 }
@@ -0,0 +1,91 @@
+use std::num::NonZeroUsize;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chat::results::CommitCheckResult;
+use crate::condition::{Action, Condition};
+use crate::error::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReachStageCondition {
+    // 1-based stage of the repository pipeline
+    pub stage: NonZeroUsize,
+}
+
+impl Condition for ReachStageCondition {
+    fn check(&self, check_results: &CommitCheckResult) -> Action {
+        if check_results
+            .pipeline
+            .as_ref()
+            .is_some_and(|p| p.reached >= self.stage.get())
+        {
+            Action::Remove
+        } else {
+            Action::None
+        }
+    }
+}
+
+impl ReachStageCondition {
+    pub fn parse(s: &str, pipeline: &[String]) -> Result<Self, Error> {
+        let stage: NonZeroUsize = s.trim().parse()?;
+        if stage.get() > pipeline.len() {
+            return Err(Error::StageOutOfPipeline {
+                stage: stage.get(),
+                stages: pipeline.len(),
+            });
+        }
+        Ok(ReachStageCondition { stage })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::*;
+    use crate::chat::results::PipelineProgress;
+
+    fn result(pipeline: Option<PipelineProgress>) -> CommitCheckResult {
+        CommitCheckResult {
+            all: BTreeSet::new(),
+            new: BTreeSet::new(),
+            conditions: BTreeMap::new(),
+            pipeline,
+        }
+    }
+
+    #[test]
+    fn reach_stage() {
+        let stages: Vec<String> = ["staging", "staging-next", "master"]
+            .map(String::from)
+            .to_vec();
+        let condition = ReachStageCondition::parse("2", &stages).unwrap();
+        let progress = |branches: &[&str]| {
+            let branches = branches.iter().map(|b| b.to_string()).collect();
+            PipelineProgress::new(&stages, &branches)
+        };
+        assert_eq!(condition.check(&result(None)), Action::None);
+        assert_eq!(
+            condition.check(&result(progress(&["staging"]))),
+            Action::None
+        );
+        assert_eq!(
+            condition.check(&result(progress(&["staging", "staging-next"]))),
+            Action::Remove
+        );
+        assert_eq!(
+            condition.check(&result(progress(&["master"]))),
+            Action::Remove
+        );
+
+        assert!(matches!(
+            ReachStageCondition::parse("4", &stages),
+            Err(Error::StageOutOfPipeline {
+                stage: 4,
+                stages: 3
+            })
+        ));
+        assert!(ReachStageCondition::parse("0", &stages).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use teloxide::{
+    ApiError, Bot, RequestError,
+    payloads::{EditMessageTextSetters, PinChatMessageSetters, SendMessageSetters},
+    prelude::Requester,
+    sugar::request::RequestLinkPreviewExt,
+    types::{ChatId, MessageId, ParseMode},
+};
+
+use crate::{
+    CommandError, chat,
+    message::{dashboard_header, dashboard_lines, split_lines},
+};
+
+// leave some room below the telegram limit of 4096
+const MAX_DASHBOARD_MESSAGE_LENGTH: usize = 4000;
+
+pub async fn refresh(bot: &Bot, chat: ChatId) -> Result<(), CommandError> {
+    let chat_resources = chat::chat_resources(chat).await?;
+    let Some(dashboard) = chat_resources.settings.read().await.dashboard.clone() else {
+        return Ok(());
+    };
+    let items = chat::dashboard_items(chat).await?;
+    let lang = chat::language(chat).await;
+    let lines = dashboard_lines(lang, &items);
+    let hash = {
+        let mut hasher = DefaultHasher::new();
+        lines.hash(&mut hasher);
+        hasher.finish()
+    };
+    let (old, old_hash) = {
+        let results = chat_resources.results.read().await;
+        (results.dashboard.clone(), results.dashboard_hash)
+    };
+    // only the update time would change
+    if !old.is_empty() && old_hash == Some(hash) {
+        return Ok(());
+    }
+    let lines = std::iter::once(dashboard_header(lang))
+        .chain(lines)
+        .collect();
+    let messages = split_lines(lines, MAX_DASHBOARD_MESSAGE_LENGTH);
+    let mut new = Vec::new();
+    for (i, text) in messages.into_iter().enumerate() {
+        if let Some(id) = old.get(i) {
+            let result = bot
+                .edit_message_text(chat, *id, &text)
+                .parse_mode(ParseMode::MarkdownV2)
+                .disable_link_preview(true)
+                .await;
+            match result {
+                Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => {
+                    new.push(*id);
+                    continue;
+                }
+                Err(e) => log::warn!(
+                    "failed to edit dashboard message {id} in {chat}, sending a new one: {e}"
+                ),
+            }
+        }
+        let sent = bot
+            .send_message(chat, text)
+            .parse_mode(ParseMode::MarkdownV2)
+            .disable_link_preview(true)
+            .disable_notification(true)
+            .await?;
+        if i == 0
+            && dashboard.pin
+            && let Err(e) = bot
+                .pin_chat_message(chat, sent.id)
+                .disable_notification(true)
+                .await
+        {
+            // usually the bot is not allowed to pin messages
+            log::warn!("failed to pin dashboard in {chat}: {e}");
+        }
+        new.push(sent.id);
+    }
+    let stale: Vec<_> = old.into_iter().filter(|id| !new.contains(id)).collect();
+    delete(bot, chat, &stale).await;
+    {
+        let mut results = chat_resources.results.write().await;
+        results.dashboard = new;
+        results.dashboard_hash = Some(hash);
+    }
+    chat_resources.save_results().await?;
+    Ok(())
+}
+
+pub async fn delete(bot: &Bot, chat: ChatId, messages: &[MessageId]) {
+    for id in messages {
+        if let Err(e) = bot.delete_message(chat, *id).await {
+            log::warn!("failed to delete dashboard message {id} in {chat}: {e}");
+        }
+    }
+}
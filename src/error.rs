@@ -5,8 +5,10 @@ use teloxide::types::ReplyParameters;
 use thiserror::Error;
 use tokio::sync::Mutex;
 
-use crate::chat::settings::SubscriberCompat;
+use crate::chat;
+use crate::chat::settings::{QuotaKind, Role, SubscriberCompat};
 use crate::github::GitHubInfo;
+use crate::i18n::{Key, Language};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -61,6 +63,11 @@ pub enum Error {
     Serde(#[from] serde_json::Error),
     #[error("unknown commit: '{0}'")]
     UnknownCommit(String),
+    #[error("ambiguous revision '{spec}', candidates: {candidates:?}")]
+    AmbiguousRevision {
+        spec: String,
+        candidates: Vec<String>,
+    },
     #[error("unknown PR/issue: '{0}'")]
     UnknownPRIssue(u64),
     #[error("unknown branch: '{0}'")]
@@ -73,6 +80,14 @@ pub enum Error {
     PRIssueExists(u64),
     #[error("branch already exists: '{0}'")]
     BranchExists(String),
+    #[error("branch pair already exists: '{0}'")]
+    BranchPairExists(String),
+    #[error("unknown branch pair: '{0}'")]
+    UnknownBranchPair(String),
+    #[error("watch rule already exists: '{0}'")]
+    WatchExists(String),
+    #[error("unknown watch rule: '{0}'")]
+    UnknownWatch(String),
     #[error("invalid os string: '{0:?}'")]
     InvalidOsString(OsString),
     #[error("invalid chat directory: '{0}'")]
@@ -95,6 +110,30 @@ pub enum Error {
     UrlParse(#[from] url::ParseError),
     #[error("can not get subscriber from message")]
     NoSubscriber,
+    #[error("attach an exported document or reply to one to import it")]
+    ImportNoDocument,
+    #[error("invalid items, nothing imported:\n{}", .0.join("\n"))]
+    ImportInvalid(Vec<String>),
+    #[error("download error: {0}")]
+    Download(#[from] teloxide::DownloadError),
+    #[error("invalid cron expression: {0}")]
+    Cron(#[from] cron::error::Error),
+    #[error("invalid template {template:?}: {reason}")]
+    InvalidTemplate { template: String, reason: String },
+    #[error("pipeline stage '{0}' does not match the branch regex")]
+    PipelineStageNotWatched(String),
+    #[error("stage {stage} is out of the pipeline of {stages} stages")]
+    StageOutOfPipeline { stage: usize, stages: usize },
+    #[error(
+        "condition '{identifier}' waits for stage {stage}, out of the pipeline of {stages} stages"
+    )]
+    ConditionStageOutOfPipeline {
+        identifier: String,
+        stage: usize,
+        stages: usize,
+    },
+    #[error("not muted")]
+    NotMuted,
     #[error("already subscribed")]
     AlreadySubscribed,
     #[error("not subscribed")]
@@ -117,6 +156,18 @@ pub enum Error {
     UnsupportedPRIssueUrl(String),
     #[error("not in an admin chat")]
     NotAdminChat,
+    #[error("permission denied, requires role {required:?}, but yours is {actual:?}")]
+    PermissionDenied { required: Role, actual: Role },
+    #[error("quota exceeded, {used} {kind} tracked in this chat, limit {limit}")]
+    QuotaExceeded {
+        kind: QuotaKind,
+        used: usize,
+        limit: usize,
+    },
+    #[error("the list has expired, run list again")]
+    ListExpired,
+    #[error("specify a user id or reply to a message of the user")]
+    NoRoleTarget,
     #[error("invalid subscriber: {0:?}")]
     InvalidSubscriber(SubscriberCompat),
 }
@@ -124,9 +175,65 @@ pub enum Error {
 impl Error {
     pub async fn report(&self, bot: &Bot, msg: &Message) -> Result<(), teloxide::RequestError> {
         log::warn!("report error to chat {}: {:?}", msg.chat.id, self);
-        bot.send_message(msg.chat.id, format!("{self}"))
+        let lang = chat::language(msg.chat.id).await;
+        bot.send_message(msg.chat.id, self.localized(lang))
             .reply_parameters(ReplyParameters::new(msg.id))
             .await?;
         Ok(())
     }
+
+    // falls back to the English description for errors not in catalogs
+    pub fn localized(&self, lang: Language) -> String {
+        let (key, id) = match self {
+            Error::UnknownCommit(id) => (Key::ErrorUnknownCommit, id.clone()),
+            Error::UnknownBranch(id) => (Key::ErrorUnknownBranch, id.clone()),
+            Error::UnknownPRIssue(id) => (Key::ErrorUnknownPRIssue, id.to_string()),
+            Error::UnknownRepository(id) => (Key::ErrorUnknownRepository, id.clone()),
+            Error::AlreadySubscribed => (Key::ErrorAlreadySubscribed, String::new()),
+            Error::NotSubscribed => (Key::ErrorNotSubscribed, String::new()),
+            Error::NotInAllowList(id) => (Key::ErrorNotInAllowList, id.to_string()),
+            Error::NotMuted => (Key::ErrorNotMuted, String::new()),
+            Error::CommitExists(id) => (Key::ErrorCommitExists, id.clone()),
+            Error::PRIssueExists(id) => (Key::ErrorPRIssueExists, id.to_string()),
+            Error::BranchExists(id) => (Key::ErrorBranchExists, id.clone()),
+            Error::BranchPairExists(id) => (Key::ErrorBranchPairExists, id.clone()),
+            Error::UnknownBranchPair(id) => (Key::ErrorUnknownBranchPair, id.clone()),
+            Error::WatchExists(id) => (Key::ErrorWatchExists, id.clone()),
+            Error::UnknownWatch(id) => (Key::ErrorUnknownWatch, id.clone()),
+            Error::ConditionExists(id) => (Key::ErrorConditionExists, id.clone()),
+            Error::UnknownCondition(id) => (Key::ErrorUnknownCondition, id.clone()),
+            Error::NoSubscriber => (Key::ErrorNoSubscriber, String::new()),
+            Error::ImportNoDocument => (Key::ErrorImportNoDocument, String::new()),
+            Error::NotAdminChat => (Key::ErrorNotAdminChat, String::new()),
+            Error::ListExpired => (Key::ErrorListExpired, String::new()),
+            Error::NoRoleTarget => (Key::ErrorNoRoleTarget, String::new()),
+            Error::AmbiguousRevision { spec, candidates } => {
+                return lang.fill(
+                    Key::ErrorAmbiguousRevision,
+                    &[("id", spec), ("candidates", &format!("{candidates:?}"))],
+                );
+            }
+            Error::PermissionDenied { required, actual } => {
+                return lang.fill(
+                    Key::ErrorPermissionDenied,
+                    &[
+                        ("required", &format!("{required:?}")),
+                        ("actual", &format!("{actual:?}")),
+                    ],
+                );
+            }
+            Error::QuotaExceeded { kind, used, limit } => {
+                return lang.fill(
+                    Key::ErrorQuotaExceeded,
+                    &[
+                        ("kind", &kind.to_string()),
+                        ("used", &used.to_string()),
+                        ("limit", &limit.to_string()),
+                    ],
+                );
+            }
+            _ => return self.to_string(),
+        };
+        lang.fill(key, &[("id", &id)])
+    }
 }
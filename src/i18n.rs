@@ -0,0 +1,428 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    En,
+    ZhCn,
+}
+
+macro_rules! keys {
+    ($($key:ident),* $(,)?) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        pub enum Key {
+            $($key),*
+        }
+
+        impl Key {
+            #[cfg(test)]
+            pub const ALL: &[Key] = &[$(Key::$key),*];
+        }
+    };
+}
+
+// catalog texts are markdown (except errors), placeholders are written as `{name}`
+keys! {
+    Subscribers,
+    CommitAllBranches,
+    CommitAutoRemoved,
+    BranchNotChanged,
+    BranchCreated,
+    BranchDeleted,
+    BranchForcePushed,
+    BranchRecreated,
+    BranchUnknownBase,
+    BranchStale,
+    BranchMovingAgain,
+    BranchNewCommit,
+    BranchNewCommits,
+    BranchTouching,
+    BranchUnlistedCommits,
+    BranchDiffStatFile,
+    BranchDiffStatFiles,
+    AndMore,
+    PRIssueOpened,
+    PRIssueMerged,
+    PRIssueClosed,
+    WatchStarted,
+    BranchCompare,
+    BranchPairLagging,
+    BranchPairCaughtUp,
+    MuteTargetChat,
+    MuteExpired,
+    Unmuted,
+    MuteSuppressedNotification,
+    MuteSuppressedNotifications,
+    DigestEvent,
+    DigestEvents,
+    CategoryCommits,
+    CategoryBranches,
+    CategoryPRIssues,
+    CategoryOthers,
+    DashboardTitle,
+    NotChecked,
+    PRIssueOpen,
+    PRIssueClosedAt,
+    StatusIn,
+    StatusState,
+    StatusHead,
+    StatusChanged,
+    ErrorUnknownCommit,
+    ErrorUnknownBranch,
+    ErrorUnknownPRIssue,
+    ErrorUnknownRepository,
+    ErrorAlreadySubscribed,
+    ErrorNotSubscribed,
+    ErrorNotInAllowList,
+    ErrorNotMuted,
+    ErrorCommitExists,
+    ErrorPRIssueExists,
+    ErrorBranchExists,
+    ErrorBranchPairExists,
+    ErrorUnknownBranchPair,
+    ErrorWatchExists,
+    ErrorUnknownWatch,
+    ErrorConditionExists,
+    ErrorUnknownCondition,
+    ErrorAmbiguousRevision,
+    ErrorNoSubscriber,
+    ErrorImportNoDocument,
+    ErrorNotAdminChat,
+    ErrorPermissionDenied,
+    ErrorQuotaExceeded,
+    ErrorListExpired,
+    ErrorNoRoleTarget,
+}
+
+static EN: &[(Key, &str)] = &[
+    (Key::Subscribers, "*subscribers*: "),
+    (
+        Key::CommitAllBranches,
+        "*all* branches containing this commit:",
+    ),
+    (Key::CommitAutoRemoved, "*auto removed* by conditions:"),
+    (Key::BranchNotChanged, "\\(not changed\\)"),
+    (Key::BranchCreated, "\\(created\\)"),
+    (Key::BranchDeleted, "\\(deleted\\)"),
+    (
+        Key::BranchForcePushed,
+        "\u{26a0}\u{fe0f} *force\\-pushed*: {dropped} dropped, {added} added, merge base `{base}`",
+    ),
+    (
+        Key::BranchRecreated,
+        "\u{26a0}\u{fe0f} *recreated* with unrelated history",
+    ),
+    (
+        Key::BranchUnknownBase,
+        "\u{26a0}\u{fe0f} *force\\-pushed*, the old head is no longer available",
+    ),
+    (
+        Key::BranchStale,
+        "\u{23f3} *stale*: not moved for {elapsed}",
+    ),
+    (Key::BranchMovingAgain, "*moving again* after {elapsed}"),
+    (Key::BranchNewCommit, "*{count} new commit*"),
+    (Key::BranchNewCommits, "*{count} new commits*"),
+    (Key::BranchTouching, " touching {paths}"),
+    (
+        Key::BranchUnlistedCommits,
+        "*more than {count} new commits*{filter}, not checked one by one",
+    ),
+    (Key::BranchDiffStatFile, "*diffstat*: {files} file changed"),
+    (
+        Key::BranchDiffStatFiles,
+        "*diffstat*: {files} files changed",
+    ),
+    (Key::AndMore, "and {count} more"),
+    (Key::PRIssueOpened, "{id} has been opened"),
+    (Key::PRIssueMerged, "{id} merged as `{commit}`"),
+    (Key::PRIssueClosed, "{id} has been closed"),
+    (
+        Key::WatchStarted,
+        "started tracking `{commit}` by watch rule `{watch}`",
+    ),
+    (
+        Key::BranchCompare,
+        "{repo}/`{branch}` is {ahead} ahead of and {behind} behind `{base}`",
+    ),
+    (
+        Key::BranchPairLagging,
+        "*lagging* by at least {threshold} commits",
+    ),
+    (Key::BranchPairCaughtUp, "*caught up*"),
+    (Key::MuteTargetChat, "this chat"),
+    (Key::MuteExpired, "{target} mute expired"),
+    (Key::Unmuted, "{target} unmuted"),
+    (
+        Key::MuteSuppressedNotification,
+        ", {count} notification suppressed while muted",
+    ),
+    (
+        Key::MuteSuppressedNotifications,
+        ", {count} notifications suppressed while muted",
+    ),
+    (
+        Key::DigestEvent,
+        "*digest*: {count} event in the last {elapsed}",
+    ),
+    (
+        Key::DigestEvents,
+        "*digest*: {count} events in the last {elapsed}",
+    ),
+    (Key::CategoryCommits, "commits"),
+    (Key::CategoryBranches, "branches"),
+    (Key::CategoryPRIssues, "PRs/issues"),
+    (Key::CategoryOthers, "others"),
+    (Key::DashboardTitle, "*dashboard*, updated at {time}"),
+    (Key::NotChecked, "\\(not checked\\)"),
+    (Key::PRIssueOpen, "open"),
+    (Key::PRIssueClosedAt, "closed at {time}"),
+    (Key::StatusIn, "*in*: {branches}"),
+    (Key::StatusState, "*state*: {state}"),
+    (Key::StatusHead, "*head*: {head}"),
+    (Key::StatusChanged, "*changed*: {elapsed} ago"),
+    (Key::ErrorUnknownCommit, "unknown commit: '{id}'"),
+    (Key::ErrorUnknownBranch, "unknown branch: '{id}'"),
+    (Key::ErrorUnknownPRIssue, "unknown PR/issue: '{id}'"),
+    (Key::ErrorUnknownRepository, "unknown repository: '{id}'"),
+    (Key::ErrorAlreadySubscribed, "already subscribed"),
+    (Key::ErrorNotSubscribed, "not subscribed"),
+    (
+        Key::ErrorNotInAllowList,
+        "chat id {id} is not in allow list",
+    ),
+    (Key::ErrorNotMuted, "not muted"),
+    (Key::ErrorCommitExists, "commit already exists: '{id}'"),
+    (Key::ErrorPRIssueExists, "PR/issue already exists: '{id}'"),
+    (Key::ErrorBranchExists, "branch already exists: '{id}'"),
+    (
+        Key::ErrorBranchPairExists,
+        "branch pair already exists: '{id}'",
+    ),
+    (Key::ErrorUnknownBranchPair, "unknown branch pair: '{id}'"),
+    (Key::ErrorWatchExists, "watch rule already exists: '{id}'"),
+    (Key::ErrorUnknownWatch, "unknown watch rule: '{id}'"),
+    (
+        Key::ErrorConditionExists,
+        "condition identifier already exists: '{id}'",
+    ),
+    (
+        Key::ErrorUnknownCondition,
+        "unknown condition identifier: '{id}'",
+    ),
+    (
+        Key::ErrorAmbiguousRevision,
+        "ambiguous revision '{id}', candidates: {candidates}",
+    ),
+    (
+        Key::ErrorNoSubscriber,
+        "can not get subscriber from message",
+    ),
+    (
+        Key::ErrorImportNoDocument,
+        "attach an exported document or reply to one to import it",
+    ),
+    (Key::ErrorNotAdminChat, "not in an admin chat"),
+    (
+        Key::ErrorPermissionDenied,
+        "permission denied, requires role {required}, but yours is {actual}",
+    ),
+    (
+        Key::ErrorQuotaExceeded,
+        "quota exceeded, {used} {kind} tracked in this chat, limit {limit}",
+    ),
+    (
+        Key::ErrorListExpired,
+        "the list has expired, run list again",
+    ),
+    (
+        Key::ErrorNoRoleTarget,
+        "specify a user id or reply to a message of the user",
+    ),
+];
+
+static ZH_CN: &[(Key, &str)] = &[
+    (Key::Subscribers, "*订阅者*："),
+    (Key::CommitAllBranches, "包含此提交的*所有*分支："),
+    (Key::CommitAutoRemoved, "已被以下条件*自动移除*："),
+    (Key::BranchNotChanged, "\\(未变化\\)"),
+    (Key::BranchCreated, "\\(已创建\\)"),
+    (Key::BranchDeleted, "\\(已删除\\)"),
+    (
+        Key::BranchForcePushed,
+        "\u{26a0}\u{fe0f} *强制推送*：丢弃 {dropped} 个，新增 {added} 个，合并基点 `{base}`",
+    ),
+    (
+        Key::BranchRecreated,
+        "\u{26a0}\u{fe0f} *已重建*，与原历史无关",
+    ),
+    (
+        Key::BranchUnknownBase,
+        "\u{26a0}\u{fe0f} *强制推送*，原头部提交已不可用",
+    ),
+    (Key::BranchStale, "\u{23f3} *停滞*：已有 {elapsed} 未更新"),
+    (Key::BranchMovingAgain, "停滞 {elapsed} 后*再次更新*"),
+    (Key::BranchNewCommit, "*{count} 个新提交*"),
+    (Key::BranchNewCommits, "*{count} 个新提交*"),
+    (Key::BranchTouching, "，涉及 {paths}"),
+    (
+        Key::BranchUnlistedCommits,
+        "*超过 {count} 个新提交*{filter}，未逐个检查",
+    ),
+    (Key::BranchDiffStatFile, "*变更统计*：{files} 个文件"),
+    (Key::BranchDiffStatFiles, "*变更统计*：{files} 个文件"),
+    (Key::AndMore, "以及另外 {count} 个"),
+    (Key::PRIssueOpened, "{id} 已打开"),
+    (Key::PRIssueMerged, "{id} 已合并为 `{commit}`"),
+    (Key::PRIssueClosed, "{id} 已关闭"),
+    (
+        Key::WatchStarted,
+        "已通过监视规则 `{watch}` 开始追踪 `{commit}`",
+    ),
+    (
+        Key::BranchCompare,
+        "{repo}/`{branch}` 领先 `{base}` {ahead} 个提交，落后 {behind} 个提交",
+    ),
+    (Key::BranchPairLagging, "*落后*至少 {threshold} 个提交"),
+    (Key::BranchPairCaughtUp, "*已追上*"),
+    (Key::MuteTargetChat, "此聊天"),
+    (Key::MuteExpired, "{target} 静音已到期"),
+    (Key::Unmuted, "{target} 已取消静音"),
+    (
+        Key::MuteSuppressedNotification,
+        "，静音期间屏蔽了 {count} 条通知",
+    ),
+    (
+        Key::MuteSuppressedNotifications,
+        "，静音期间屏蔽了 {count} 条通知",
+    ),
+    (
+        Key::DigestEvent,
+        "*摘要*：过去 {elapsed} 内有 {count} 个事件",
+    ),
+    (
+        Key::DigestEvents,
+        "*摘要*：过去 {elapsed} 内有 {count} 个事件",
+    ),
+    (Key::CategoryCommits, "提交"),
+    (Key::CategoryBranches, "分支"),
+    (Key::CategoryPRIssues, "PR/issue"),
+    (Key::CategoryOthers, "其他"),
+    (Key::DashboardTitle, "*仪表盘*，更新于 {time}"),
+    (Key::NotChecked, "\\(尚未检查\\)"),
+    (Key::PRIssueOpen, "开启中"),
+    (Key::PRIssueClosedAt, "已于 {time} 关闭"),
+    (Key::StatusIn, "*所在分支*：{branches}"),
+    (Key::StatusState, "*状态*：{state}"),
+    (Key::StatusHead, "*最新提交*：{head}"),
+    (Key::StatusChanged, "*更新于*：{elapsed}前"),
+    (Key::ErrorUnknownCommit, "未知提交：'{id}'"),
+    (Key::ErrorUnknownBranch, "未知分支：'{id}'"),
+    (Key::ErrorUnknownPRIssue, "未知 PR/issue：'{id}'"),
+    (Key::ErrorUnknownRepository, "未知仓库：'{id}'"),
+    (Key::ErrorAlreadySubscribed, "已经订阅"),
+    (Key::ErrorNotSubscribed, "尚未订阅"),
+    (Key::ErrorNotInAllowList, "聊天 {id} 不在允许列表中"),
+    (Key::ErrorNotMuted, "未被静音"),
+    (Key::ErrorCommitExists, "提交已存在：'{id}'"),
+    (Key::ErrorPRIssueExists, "PR/issue 已存在：'{id}'"),
+    (Key::ErrorBranchExists, "分支已存在：'{id}'"),
+    (Key::ErrorBranchPairExists, "分支对已存在：'{id}'"),
+    (Key::ErrorUnknownBranchPair, "未知分支对：'{id}'"),
+    (Key::ErrorWatchExists, "监视规则已存在：'{id}'"),
+    (Key::ErrorUnknownWatch, "未知监视规则：'{id}'"),
+    (Key::ErrorConditionExists, "条件标识符已存在：'{id}'"),
+    (Key::ErrorUnknownCondition, "未知条件标识符：'{id}'"),
+    (
+        Key::ErrorAmbiguousRevision,
+        "修订 '{id}' 有歧义，候选：{candidates}",
+    ),
+    (Key::ErrorNoSubscriber, "无法从消息中获取订阅者"),
+    (
+        Key::ErrorImportNoDocument,
+        "请附上导出的文件或回复包含该文件的消息以导入",
+    ),
+    (Key::ErrorNotAdminChat, "不在管理聊天中"),
+    (
+        Key::ErrorPermissionDenied,
+        "权限不足，需要角色 {required}，你的角色为 {actual}",
+    ),
+    (
+        Key::ErrorQuotaExceeded,
+        "超出配额，此聊天已追踪 {used} 个 {kind}，上限 {limit}",
+    ),
+    (Key::ErrorListExpired, "列表已过期，请重新执行 list"),
+    (Key::ErrorNoRoleTarget, "请指定用户 ID 或回复该用户的消息"),
+];
+
+impl Language {
+    fn catalog(self) -> &'static [(Key, &'static str)] {
+        match self {
+            Language::En => EN,
+            Language::ZhCn => ZH_CN,
+        }
+    }
+
+    // falls back to English if the key is missing
+    pub fn text(self, key: Key) -> &'static str {
+        lookup(self.catalog(), key)
+            .or_else(|| lookup(EN, key))
+            .unwrap_or_default()
+    }
+
+    pub fn fill(self, key: Key, args: &[(&str, &str)]) -> String {
+        let mut result = self.text(key).to_string();
+        for (name, value) in args {
+            result = result.replace(&format!("{{{name}}}"), value);
+        }
+        result
+    }
+}
+
+fn lookup(catalog: &'static [(Key, &'static str)], key: Key) -> Option<&'static str> {
+    catalog
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, text)| *text)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use clap::ValueEnum;
+    use regex::Regex;
+
+    use super::*;
+
+    #[test]
+    fn catalogs_complete() {
+        let all: BTreeSet<Key> = Key::ALL.iter().copied().collect();
+        for language in Language::value_variants() {
+            let catalog = language.catalog();
+            let keys: BTreeSet<Key> = catalog.iter().map(|(k, _)| *k).collect();
+            assert_eq!(keys, all, "keys of {language:?}");
+            assert_eq!(catalog.len(), all.len(), "duplicated keys in {language:?}");
+        }
+    }
+
+    #[test]
+    fn catalogs_placeholders_match() {
+        let placeholder = Regex::new(r"\{[a-z_]+\}").unwrap();
+        let placeholders = |text: &str| -> BTreeSet<String> {
+            placeholder
+                .find_iter(text)
+                .map(|m| m.as_str().to_string())
+                .collect()
+        };
+        for language in Language::value_variants() {
+            for key in Key::ALL {
+                assert_eq!(
+                    placeholders(language.text(*key)),
+                    placeholders(Language::En.text(*key)),
+                    "placeholders of {key:?} in {language:?}"
+                );
+            }
+        }
+    }
+}
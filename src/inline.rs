@@ -0,0 +1,197 @@
+use std::{
+    collections::BTreeMap,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use futures::future::join_all;
+use teloxide::{
+    Bot,
+    payloads::AnswerInlineQuerySetters,
+    prelude::{Requester, ResponseResult},
+    types::{
+        ChatId, InlineQuery, InlineQueryResult, InlineQueryResultArticle, InputMessageContent,
+        InputMessageContentText, LinkPreviewOptions, ParseMode, UserId,
+    },
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    chat,
+    error::Error,
+    message::{
+        branch_status_message, commit_status_message, pr_issue_status_message, short_commit,
+        split_lines,
+    },
+    utils::first_line,
+};
+
+// the limit of telegram
+const MAX_INLINE_RESULTS: usize = 50;
+const INLINE_CACHE_TIME: u32 = 10;
+const MEMBERSHIP_CACHE_DURATION: Duration = Duration::from_secs(5 * 60);
+// membership of this many chats is checked at once
+const MAX_CONCURRENT_MEMBER_CHECKS: usize = 16;
+// the limit of telegram for message texts
+const MAX_ARTICLE_LENGTH: usize = 4096;
+
+// (is member, checked at)
+type Membership = BTreeMap<(ChatId, UserId), (bool, Instant)>;
+
+// inline queries are sent on every keystroke, avoid asking telegram each time
+static MEMBERSHIP: LazyLock<Mutex<Membership>> = LazyLock::new(Default::default);
+
+pub async fn handle_inline_query(bot: Bot, query: InlineQuery) -> ResponseResult<()> {
+    log::debug!("inline query = {query:?}");
+    let results = match search(&bot, query.from.id, &query.query).await {
+        Ok(results) => results,
+        Err(e) => {
+            log::warn!("failed to search for inline query {:?}: {e}", query.query);
+            Vec::new()
+        }
+    };
+    bot.answer_inline_query(query.id, results)
+        .is_personal(true)
+        .cache_time(INLINE_CACHE_TIME)
+        .await?;
+    Ok(())
+}
+
+async fn is_member(bot: &Bot, chat: ChatId, user: UserId) -> bool {
+    // the private chat with the user
+    if chat == ChatId::from(user) {
+        return true;
+    }
+    if !chat.is_group() && !chat.is_channel_or_supergroup() {
+        return false;
+    }
+    if let Some((member, at)) = MEMBERSHIP.lock().await.get(&(chat, user))
+        && at.elapsed() < MEMBERSHIP_CACHE_DURATION
+    {
+        return *member;
+    }
+    let member = match bot.get_chat_member(chat, user).await {
+        Ok(member) => member.is_present(),
+        Err(e) => {
+            log::debug!("failed to get member {user} of {chat}: {e}");
+            false
+        }
+    };
+    let mut membership = MEMBERSHIP.lock().await;
+    membership.retain(|_, (_, at)| at.elapsed() < MEMBERSHIP_CACHE_DURATION);
+    membership.insert((chat, user), (member, Instant::now()));
+    member
+}
+
+fn article(id: String, title: String, description: &str, text: String) -> InlineQueryResult {
+    // whole lines are kept
+    let text = split_lines(
+        text.lines().map(str::to_owned).collect(),
+        MAX_ARTICLE_LENGTH,
+    )
+    .into_iter()
+    .next()
+    .unwrap_or_default();
+    let content = InputMessageContent::Text(
+        InputMessageContentText::new(text)
+            .parse_mode(ParseMode::MarkdownV2)
+            .link_preview_options(LinkPreviewOptions {
+                is_disabled: true,
+                url: None,
+                prefer_small_media: false,
+                prefer_large_media: false,
+                show_above_text: false,
+            }),
+    );
+    InlineQueryResult::Article(
+        InlineQueryResultArticle::new(id, title, content).description(description),
+    )
+}
+
+// commits, PRs/issues and branches tracked in allowed chats the user belongs to
+async fn search(bot: &Bot, user: UserId, text: &str) -> Result<Vec<InlineQueryResult>, Error> {
+    let text = text.trim().to_lowercase();
+    let chats: Vec<_> = chat::chats().await?.into_iter().collect();
+    // result ids only need to be unique within an answer
+    let mut results = Vec::new();
+    for batch in chats.chunks(MAX_CONCURRENT_MEMBER_CHECKS) {
+        let members = join_all(batch.iter().map(|chat| is_member(bot, *chat, user))).await;
+        for (chat, member) in batch.iter().zip(members) {
+            if !member {
+                continue;
+            }
+            // a broken chat should not hide the results of others
+            if let Err(e) = search_chat(*chat, &text, &mut results).await {
+                log::warn!("failed to search chat {chat} for inline query: {e}");
+            }
+            if results.len() >= MAX_INLINE_RESULTS {
+                results.truncate(MAX_INLINE_RESULTS);
+                return Ok(results);
+            }
+        }
+    }
+    Ok(results)
+}
+
+async fn search_chat(
+    chat: ChatId,
+    text: &str,
+    results: &mut Vec<InlineQueryResult>,
+) -> Result<(), Error> {
+    let matches = |fields: &[&str]| {
+        text.is_empty()
+            || fields
+                .iter()
+                .any(|field| field.to_lowercase().contains(text))
+    };
+    let lang = chat::language(chat).await;
+    for repo in chat::repos(chat).await? {
+        if results.len() >= MAX_INLINE_RESULTS {
+            break;
+        }
+        let resources = chat::resources_chat_repo(chat, repo.clone()).await?;
+        let settings = resources.settings.read().await.clone();
+        let chat_results = resources.results.read().await.clone();
+        for (hash, commit) in &settings.commits {
+            if !matches(&[hash, &commit.notify.comment]) {
+                continue;
+            }
+            results.push(article(
+                results.len().to_string(),
+                format!("[{repo}] commit {}", short_commit(hash)),
+                first_line(&commit.notify.comment),
+                commit_status_message(lang, &repo, hash, commit, chat_results.commits.get(hash)),
+            ));
+        }
+        for (id, pr_issue) in &settings.pr_issues {
+            let id_text = id.to_string();
+            if !matches(&[&id_text, &format!("#{id}"), &pr_issue.notify.comment]) {
+                continue;
+            }
+            results.push(article(
+                results.len().to_string(),
+                format!("[{repo}] #{id}"),
+                first_line(&pr_issue.notify.comment),
+                pr_issue_status_message(lang, &repo, *id, pr_issue),
+            ));
+        }
+        for (branch, branch_settings) in &settings.branches {
+            if !matches(&[branch, &branch_settings.notify.comment]) {
+                continue;
+            }
+            results.push(article(
+                results.len().to_string(),
+                format!("[{repo}] branch {branch}"),
+                first_line(&branch_settings.notify.comment),
+                branch_status_message(
+                    lang,
+                    &repo,
+                    branch,
+                    branch_settings,
+                    chat_results.branches.get(branch),
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
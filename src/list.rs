@@ -0,0 +1,490 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+    sync::LazyLock,
+};
+
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    types::{ChatId, InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup, UserId},
+    utils::markdown,
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    chat::{
+        self,
+        settings::{NotifySettings, Subscriber},
+    },
+    command::{ListFilter, ListKind},
+    error::Error,
+    utils::first_line,
+};
+
+// leaves room for the footer within the message limit
+const MAX_LIST_PAGE_LENGTH: usize = 3500;
+// longer lists are sent as a file instead
+const MAX_LIST_PAGES: usize = 10;
+// buttons of older lists stop working
+const MAX_LIST_QUERIES: usize = 256;
+const MAX_DETAIL_LENGTH: usize = 100;
+
+// queries are kept in memory, callback data is limited to 64 bytes
+static LIST_QUERIES: LazyLock<Mutex<BTreeMap<u64, ListQuery>>> = LazyLock::new(Default::default);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ListTerm {
+    #[serde(rename = "l")]
+    pub query: u64,
+    #[serde(rename = "p")]
+    pub page: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListQuery {
+    pub chat: ChatId,
+    pub filter: ListFilter,
+    pub subscribers: bool,
+    subscriber: Option<SubscriberFilter>,
+}
+
+#[derive(Debug, Clone)]
+enum SubscriberFilter {
+    User(UserId),
+    // lowercase, matched against the display text of mentions
+    Name(String),
+}
+
+impl SubscriberFilter {
+    fn new(value: &str, sender: Option<UserId>) -> Result<Self, Error> {
+        if value == "me" {
+            sender.map(Self::User).ok_or(Error::NoSubscriber)
+        } else if let Ok(id) = value.parse() {
+            Ok(Self::User(UserId(id)))
+        } else {
+            Ok(Self::Name(value.to_lowercase()))
+        }
+    }
+
+    fn matches(&self, subscribers: &BTreeSet<Subscriber>) -> bool {
+        subscribers.iter().any(|subscriber| match self {
+            Self::User(id) => subscriber.user_id() == Some(*id),
+            Self::Name(name) => subscriber.display_name().to_lowercase().contains(name),
+        })
+    }
+}
+
+impl ListQuery {
+    pub fn new(
+        chat: ChatId,
+        filter: ListFilter,
+        subscribers: bool,
+        sender: Option<UserId>,
+    ) -> Result<Self, Error> {
+        let subscriber = filter
+            .subscriber
+            .as_deref()
+            .map(|s| SubscriberFilter::new(s, sender))
+            .transpose()?;
+        Ok(Self {
+            chat,
+            filter,
+            subscribers,
+            subscriber,
+        })
+    }
+
+    fn matches(&self, kind: ListKind, id: &str, detail: &str, notify: &NotifySettings) -> bool {
+        if self.filter.kind.is_some_and(|k| k != kind) {
+            return false;
+        }
+        if let Some(subscriber) = &self.subscriber
+            && !subscriber.matches(&notify.subscribers)
+        {
+            return false;
+        }
+        match &self.filter.search {
+            Some(search) => {
+                let search = search.to_lowercase();
+                [id, detail, &notify.comment]
+                    .iter()
+                    .any(|text| text.to_lowercase().contains(&search))
+            }
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListEntry {
+    pub repo: String,
+    pub kind: ListKind,
+    pub id: String,
+    pub detail: String,
+    // display names, only collected if requested
+    pub subscribers: Vec<String>,
+}
+
+fn kind_title(kind: ListKind) -> &'static str {
+    match kind {
+        ListKind::Commit => "commits",
+        ListKind::Pr => "PRs/issues",
+        ListKind::Branch => "branches",
+        ListKind::BranchPair => "branch pairs",
+        ListKind::Watch => "watch rules",
+    }
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() > MAX_DETAIL_LENGTH {
+        let truncated: String = text.chars().take(MAX_DETAIL_LENGTH).collect();
+        format!("{truncated}…")
+    } else {
+        text.to_string()
+    }
+}
+
+// sorted by repository, kind and id
+pub async fn entries(query: &ListQuery) -> Result<Vec<ListEntry>, Error> {
+    let repos = chat::repos(query.chat).await?;
+    if let Some(repo) = &query.filter.repo
+        && !repos.contains(repo)
+    {
+        return Err(Error::UnknownRepository(repo.clone()));
+    }
+    let mut entries = Vec::new();
+    for repo in repos {
+        if query.filter.repo.as_ref().is_some_and(|r| *r != repo) {
+            continue;
+        }
+        let resources = chat::resources_chat_repo(query.chat, repo.clone()).await?;
+        let settings = resources.settings.read().await.clone();
+        let mut push = |kind, id: String, detail: String, notify: &NotifySettings| {
+            if query.matches(kind, &id, &detail, notify) {
+                let subscribers = if query.subscribers {
+                    notify
+                        .subscribers
+                        .iter()
+                        .map(Subscriber::display_name)
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                entries.push(ListEntry {
+                    repo: repo.clone(),
+                    kind,
+                    id,
+                    detail: truncate(&detail),
+                    subscribers,
+                });
+            }
+        };
+        for (hash, s) in &settings.commits {
+            push(
+                ListKind::Commit,
+                hash.clone(),
+                first_line(&s.notify.comment).to_string(),
+                &s.notify,
+            );
+        }
+        for (id, s) in &settings.pr_issues {
+            push(ListKind::Pr, id.to_string(), s.url.to_string(), &s.notify);
+        }
+        for (branch, s) in &settings.branches {
+            push(
+                ListKind::Branch,
+                branch.clone(),
+                first_line(&s.notify.comment).to_string(),
+                &s.notify,
+            );
+        }
+        for (key, s) in &settings.branch_pairs {
+            push(
+                ListKind::BranchPair,
+                key.clone(),
+                first_line(&s.notify.comment).to_string(),
+                &s.notify,
+            );
+        }
+        for (identifier, s) in &settings.watches {
+            push(
+                ListKind::Watch,
+                identifier.clone(),
+                s.message_regex.to_string(),
+                &s.notify,
+            );
+        }
+    }
+    Ok(entries)
+}
+
+// headers are repeated on every page
+fn entry_lines(entry: &ListEntry, last: Option<(&str, ListKind)>) -> Vec<String> {
+    let mut lines = Vec::new();
+    if last.map(|(repo, _)| repo) != Some(entry.repo.as_str()) {
+        lines.push(format!("*{}*", markdown::escape(&entry.repo)));
+    }
+    if last != Some((entry.repo.as_str(), entry.kind)) {
+        lines.push(format!("  *{}*:", markdown::escape(kind_title(entry.kind))));
+    }
+    lines.push(format!("  \\- `{}`", markdown::escape(&entry.id)));
+    if !entry.detail.is_empty() {
+        lines.push(format!("    {}", markdown::escape(&entry.detail)));
+    }
+    if !entry.subscribers.is_empty() {
+        lines.push(format!(
+            "    subscribers: {}",
+            markdown::escape(&truncate(&entry.subscribers.join(", ")))
+        ));
+    }
+    lines
+}
+
+// each line also takes a line break and a quote marker
+fn lines_length(lines: &[String]) -> usize {
+    lines.iter().map(|line| line.chars().count() + 2).sum()
+}
+
+// entries are packed into pages by their rendered length, like `split_lines`
+fn page_ranges(entries: &[ListEntry]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut length = 0;
+    let mut last: Option<(&str, ListKind)> = None;
+    for (i, entry) in entries.iter().enumerate() {
+        let mut added = lines_length(&entry_lines(entry, last));
+        if i > start && length + added > MAX_LIST_PAGE_LENGTH {
+            ranges.push(start..i);
+            start = i;
+            length = 0;
+            added = lines_length(&entry_lines(entry, None));
+        }
+        length += added;
+        last = Some((entry.repo.as_str(), entry.kind));
+    }
+    ranges.push(start..entries.len());
+    ranges
+}
+
+pub fn page_count(entries: &[ListEntry]) -> usize {
+    page_ranges(entries).len()
+}
+
+pub fn too_long(entries: &[ListEntry]) -> bool {
+    page_count(entries) > MAX_LIST_PAGES
+}
+
+pub fn page_markdown(entries: &[ListEntry], page: usize, footer: &str) -> String {
+    let ranges = page_ranges(entries);
+    let pages = ranges.len();
+    let mut lines = Vec::new();
+    let mut last: Option<(&str, ListKind)> = None;
+    for entry in ranges
+        .get(page)
+        .map(|range| &entries[range.clone()])
+        .unwrap_or_default()
+    {
+        lines.extend(entry_lines(entry, last));
+        last = Some((entry.repo.as_str(), entry.kind));
+    }
+    if lines.is_empty() {
+        lines.push("\\(nothing\\)".to_string());
+    }
+    let mut result = markdown::expandable_blockquote(&lines.join("\n"));
+    if pages > 1 {
+        result.push_str(&format!(
+            "\npage {}/{pages}, {} items",
+            page + 1,
+            entries.len()
+        ));
+    }
+    if !footer.is_empty() {
+        result.push('\n');
+        result.push_str(footer);
+    }
+    result
+}
+
+pub fn plain_text(entries: &[ListEntry]) -> String {
+    let mut result = String::new();
+    for entry in entries {
+        result.push_str(&format!(
+            "[{repo}] {kind}: {id}",
+            repo = entry.repo,
+            kind = kind_title(entry.kind),
+            id = entry.id
+        ));
+        if !entry.detail.is_empty() {
+            result.push_str(&format!("\n    {}", entry.detail));
+        }
+        if !entry.subscribers.is_empty() {
+            result.push_str(&format!(
+                "\n    subscribers: {}",
+                entry.subscribers.join(", ")
+            ));
+        }
+        result.push('\n');
+    }
+    result
+}
+
+pub async fn quota_markdown(chat: ChatId) -> Result<String, Error> {
+    let usage: Vec<_> = chat::quota_usage(chat)
+        .await?
+        .into_iter()
+        .map(|(kind, used, limit)| format!("{kind}: {used}/{limit}"))
+        .collect();
+    Ok(format!("*quota*: {}", markdown::escape(&usage.join(", "))))
+}
+
+pub async fn store(query: ListQuery) -> u64 {
+    let mut queries = LIST_QUERIES.lock().await;
+    let id = queries.last_key_value().map(|(id, _)| id + 1).unwrap_or(0);
+    queries.insert(id, query);
+    while queries.len() > MAX_LIST_QUERIES {
+        queries.pop_first();
+    }
+    id
+}
+
+pub async fn load(id: u64) -> Result<ListQuery, Error> {
+    LIST_QUERIES
+        .lock()
+        .await
+        .get(&id)
+        .cloned()
+        .ok_or(Error::ListExpired)
+}
+
+pub fn page_markup(query: u64, page: usize, pages: usize) -> Result<InlineKeyboardMarkup, Error> {
+    let mut buttons = Vec::new();
+    if page > 0 {
+        let data = serde_json::to_string(&ListTerm {
+            query,
+            page: page - 1,
+        })?;
+        buttons.push(InlineKeyboardButton::new(
+            "« previous",
+            InlineKeyboardButtonKind::CallbackData(data),
+        ));
+    }
+    if page + 1 < pages {
+        let data = serde_json::to_string(&ListTerm {
+            query,
+            page: page + 1,
+        })?;
+        buttons.push(InlineKeyboardButton::new(
+            "next »",
+            InlineKeyboardButtonKind::CallbackData(data),
+        ));
+    }
+    Ok(InlineKeyboardMarkup::new([buttons]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(repo: &str, kind: ListKind, id: usize) -> ListEntry {
+        ListEntry {
+            repo: repo.to_string(),
+            kind,
+            id: format!("{id:040}"),
+            detail: "x".repeat(MAX_DETAIL_LENGTH),
+            subscribers: Vec::new(),
+        }
+    }
+
+    fn filter() -> ListFilter {
+        ListFilter {
+            repo: None,
+            kind: None,
+            subscriber: None,
+            search: None,
+        }
+    }
+
+    fn notify(subscribers: &[&Subscriber]) -> NotifySettings {
+        NotifySettings {
+            comment: "Fix the build".to_string(),
+            subscribers: subscribers.iter().map(|s| (*s).clone()).collect(),
+            mute: None,
+        }
+    }
+
+    #[test]
+    fn pages_by_length() {
+        let entries: Vec<_> = (0..100)
+            .map(|id| entry(if id < 50 { "a" } else { "b" }, ListKind::Commit, id))
+            .collect();
+        let ranges = page_ranges(&entries);
+        assert!(ranges.len() > 1);
+        assert_eq!(ranges.first().unwrap().start, 0);
+        assert_eq!(ranges.last().unwrap().end, entries.len());
+        assert!(ranges.windows(2).all(|w| w[0].end == w[1].start));
+        for page in 0..ranges.len() {
+            let text = page_markdown(&entries, page, "");
+            assert!(text.chars().count() <= MAX_LIST_PAGE_LENGTH + 100);
+            // headers are repeated on every page
+            assert!(text.starts_with("**>*"));
+            assert!(text.contains("*commits*"));
+        }
+        // short entries fit on one page
+        let entries: Vec<_> = (0..3).map(|id| entry("a", ListKind::Pr, id)).collect();
+        assert_eq!(page_count(&entries), 1);
+        assert_eq!(page_count(&[]), 1);
+        assert!(page_markdown(&[], 0, "").contains("nothing"));
+    }
+
+    #[test]
+    fn filter_subscribers() {
+        let linked = Subscriber::Telegram {
+            markdown_mention: "[Alice \\(dev\\)](tg://user?id=42)".to_string(),
+            user_id: Some(UserId(42)),
+        };
+        let mentioned = Subscriber::Telegram {
+            markdown_mention: "@bob\\_smith".to_string(),
+            user_id: None,
+        };
+        assert_eq!(linked.display_name(), "Alice (dev)");
+        assert_eq!(mentioned.display_name(), "@bob_smith");
+
+        let query = |subscriber: &str| {
+            let filter = ListFilter {
+                subscriber: Some(subscriber.to_string()),
+                ..filter()
+            };
+            ListQuery::new(ChatId(1), filter, false, Some(UserId(42))).unwrap()
+        };
+        let matches = |query: &ListQuery, subscribers: &[&Subscriber]| {
+            query.matches(ListKind::Commit, "abc", "", &notify(subscribers))
+        };
+        assert!(matches(&query("me"), &[&linked]));
+        assert!(!matches(&query("me"), &[&mentioned]));
+        assert!(matches(&query("42"), &[&linked, &mentioned]));
+        assert!(matches(&query("alice"), &[&linked]));
+        assert!(matches(&query("BOB_"), &[&mentioned]));
+        // links are not part of the name
+        assert!(!matches(&query("user"), &[&linked]));
+        assert!(!matches(&query("tg"), &[&linked, &mentioned]));
+    }
+
+    #[test]
+    fn filter_kind_and_search() {
+        let query = |kind, search: &str| {
+            let filter = ListFilter {
+                kind,
+                search: Some(search.to_string()),
+                ..filter()
+            };
+            ListQuery::new(ChatId(1), filter, false, None).unwrap()
+        };
+        let notify = notify(&[]);
+        assert!(query(None, "abc").matches(ListKind::Commit, "abcdef", "", &notify));
+        assert!(query(None, "BUILD").matches(ListKind::Branch, "main", "", &notify));
+        assert!(query(None, "nixos").matches(ListKind::Pr, "1", "https://nixos.org", &notify));
+        assert!(!query(None, "missing").matches(ListKind::Commit, "abc", "", &notify));
+        assert!(!query(Some(ListKind::Pr), "abc").matches(ListKind::Commit, "abc", "", &notify));
+        assert!(query(Some(ListKind::Pr), "1").matches(ListKind::Pr, "1", "", &notify));
+    }
+}
@@ -1,13 +1,19 @@
 mod chat;
 mod command;
 mod condition;
+mod dashboard;
 mod error;
 mod github;
+mod i18n;
+mod inline;
+mod list;
 mod message;
 mod migration;
+mod notify;
 mod options;
 mod repo;
 mod resources;
+mod template;
 mod update;
 mod utils;
 
@@ -16,6 +22,8 @@ use std::collections::BTreeSet;
 use std::env;
 use std::fmt;
 use std::str::FromStr;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
 
 use chrono::Utc;
 use clap::crate_version;
@@ -25,7 +33,10 @@ use github::GitHubInfo;
 use regex::Regex;
 use serde::Deserialize;
 use serde::Serialize;
+use teloxide::ApiError;
+use teloxide::RequestError;
 use teloxide::dispatching::dialogue::GetChatId;
+use teloxide::net::Download;
 use teloxide::payloads;
 use teloxide::payloads::SendMessage;
 use teloxide::prelude::*;
@@ -34,8 +45,11 @@ use teloxide::sugar::request::RequestLinkPreviewExt;
 use teloxide::types::InlineKeyboardButton;
 use teloxide::types::InlineKeyboardButtonKind;
 use teloxide::types::InlineKeyboardMarkup;
+use teloxide::types::InputFile;
 use teloxide::types::ParseMode;
+use teloxide::types::ReplyParameters;
 use teloxide::types::User;
+use teloxide::types::UserId;
 use teloxide::update_listeners;
 use teloxide::utils::command::BotCommands;
 use teloxide::utils::markdown;
@@ -44,20 +58,36 @@ use tokio::time::sleep;
 use url::Url;
 
 use crate::chat::results::PRIssueCheckResult;
+use crate::chat::settings::BranchPairSettings;
 use crate::chat::settings::BranchSettings;
+use crate::chat::settings::ChatRepoSettings;
 use crate::chat::settings::CommitSettings;
+use crate::chat::settings::DashboardSettings;
+use crate::chat::settings::Delivery;
+use crate::chat::settings::DigestSettings;
 use crate::chat::settings::NotifySettings;
 use crate::chat::settings::PRIssueSettings;
+use crate::chat::settings::QuietSettings;
+use crate::chat::settings::Role;
 use crate::chat::settings::Subscriber;
+use crate::chat::settings::WatchSettings;
 use crate::condition::Action;
 use crate::condition::GeneralCondition;
 use crate::condition::in_branch::InBranchCondition;
+use crate::i18n::Key;
+use crate::i18n::Language;
+use crate::list::ListTerm;
 use crate::message::branch_check_message;
+use crate::message::branch_compare_message;
 use crate::message::commit_check_message;
+use crate::message::mute_summary_message;
 use crate::message::pr_issue_id_pretty;
+use crate::message::split_lines;
 use crate::message::subscriber_from_msg;
+use crate::message::time_pretty;
 use crate::repo::pr_issue_url;
 use crate::repo::settings::ConditionSettings;
+use crate::template::TemplateKind;
 use crate::update::update_and_report_error;
 use crate::utils::modify_subscriber_set;
 use crate::utils::read_json_strict;
@@ -98,9 +128,11 @@ async fn run() {
     let command_handler = teloxide::filter_command::<BCommand, _>().endpoint(answer);
     let message_handler = Update::filter_message().branch(command_handler);
     let callback_handler = Update::filter_callback_query().endpoint(handle_callback_query);
+    let inline_handler = Update::filter_inline_query().endpoint(inline::handle_inline_query);
     let handler = dptree::entry()
         .branch(message_handler)
-        .branch(callback_handler);
+        .branch(callback_handler)
+        .branch(inline_handler);
     let mut dispatcher = Dispatcher::builder(bot.clone(), handler)
         .enable_ctrlc_handler()
         .build();
@@ -118,6 +150,9 @@ async fn run() {
     if let Err(e) = chat::resources::RESOURCES_MAP.clear().await {
         log::error!("failed to clear resources map for chats: {e}");
     }
+    if let Err(e) = chat::resources::CHAT_RESOURCES_MAP.clear().await {
+        log::error!("failed to clear chat-level resources map: {e}");
+    }
     log::info!("cleaning up resources for repositories");
     if let Err(e) = repo::resources::RESOURCES_MAP.clear().await {
         log::error!("failed to clear resources map for repositories: {e}");
@@ -193,27 +228,43 @@ async fn answer(bot: Bot, msg: Message, bc: BCommand) -> ResponseResult<()> {
         BCommand::Add(input) => format!("pr-add {input}"),
     };
     let result = match command::parse(cmd_str) {
-        Ok(command) => {
+        Ok(command) => 'dispatch: {
             log::debug!("command: {command:?}");
+            if let Err(e) = ensure_role(&bot, &msg, command.required_role()).await {
+                break 'dispatch Err(e);
+            }
             let (bot, msg) = (bot.clone(), msg.clone());
             match command {
                 command::Notifier::ChatId => return_chat_id(bot, msg).await,
+                command::Notifier::ChatAllow { chat } => chat_allow(bot, msg, ChatId(chat)).await,
+                command::Notifier::ChatDeny { chat, data } => {
+                    chat_deny(bot, msg, ChatId(chat), data).await
+                }
+                command::Notifier::ChatList => chat_list(bot, msg).await,
+                command::Notifier::ChatQuota { chat, options } => {
+                    chat_quota(bot, msg, ChatId(chat), options).await
+                }
+                command::Notifier::AccessRequest => access_request(bot, msg).await,
                 command::Notifier::RepoAdd { name, url } => repo_add(bot, msg, name, url).await,
-                command::Notifier::RepoEdit {
-                    name,
-                    branch_regex,
-                    github_info,
-                    clear_github_info,
-                } => repo_edit(bot, msg, name, branch_regex, github_info, clear_github_info).await,
+                command::Notifier::RepoEdit { name, options } => {
+                    repo_edit(bot, msg, name, options).await
+                }
                 command::Notifier::RepoRemove { name } => repo_remove(bot, msg, name).await,
                 command::Notifier::CommitAdd {
                     repo,
                     hash,
                     comment,
-                } => commit_add(bot, msg, repo, hash, comment, None).await,
+                    living,
+                } => commit_add(bot, msg, repo, hash, comment, None, living).await,
                 command::Notifier::CommitRemove { repo, hash } => {
                     commit_remove(bot, msg, repo, hash).await
                 }
+                command::Notifier::CommitEdit {
+                    repo,
+                    hash,
+                    notify,
+                    options,
+                } => commit_edit(bot, msg, repo, hash, notify, options).await,
                 command::Notifier::CommitCheck { repo, hash } => {
                     commit_check(bot, msg, repo, hash).await
                 }
@@ -246,6 +297,21 @@ async fn answer(bot: Bot, msg: Message, bc: BCommand) -> ResponseResult<()> {
                     Some((repo, id)) => pr_issue_remove(bot, msg, repo, id).await,
                     None => Ok(()),
                 },
+                command::Notifier::PrEdit {
+                    repo_or_url,
+                    id,
+                    notify,
+                    url,
+                } => match report_error(
+                    &bot,
+                    &msg,
+                    resolve_repo_or_url_and_id(repo_or_url, id).await,
+                )
+                .await?
+                {
+                    Some((repo, id)) => pr_issue_edit(bot, msg, repo, id, notify, url).await,
+                    None => Ok(()),
+                },
                 command::Notifier::PrCheck { repo_or_url, id } => match report_error(
                     &bot,
                     &msg,
@@ -270,12 +336,20 @@ async fn answer(bot: Bot, msg: Message, bc: BCommand) -> ResponseResult<()> {
                     Some((repo, id)) => pr_issue_subscribe(bot, msg, repo, id, unsubscribe).await,
                     None => Ok(()),
                 },
-                command::Notifier::BranchAdd { repo, branch } => {
-                    branch_add(bot, msg, repo, branch).await
-                }
+                command::Notifier::BranchAdd {
+                    repo,
+                    branch,
+                    options,
+                } => branch_add(bot, msg, repo, branch, options.into()).await,
                 command::Notifier::BranchRemove { repo, branch } => {
                     branch_remove(bot, msg, repo, branch).await
                 }
+                command::Notifier::BranchEdit {
+                    repo,
+                    branch,
+                    notify,
+                    options,
+                } => branch_edit(bot, msg, repo, branch, notify, options).await,
                 command::Notifier::BranchCheck { repo, branch } => {
                     branch_check(bot, msg, repo, branch).await
                 }
@@ -284,6 +358,76 @@ async fn answer(bot: Bot, msg: Message, bc: BCommand) -> ResponseResult<()> {
                     branch,
                     unsubscribe,
                 } => branch_subscribe(bot, msg, repo, branch, unsubscribe).await,
+                command::Notifier::BranchPairAdd {
+                    repo,
+                    branch,
+                    base,
+                    threshold,
+                } => branch_pair_add(bot, msg, repo, branch, base, threshold).await,
+                command::Notifier::BranchPairRemove { repo, branch, base } => {
+                    branch_pair_remove(bot, msg, repo, branch, base).await
+                }
+                command::Notifier::BranchCompare { repo, branch, base } => {
+                    branch_compare(bot, msg, repo, branch, base).await
+                }
+                command::Notifier::WatchAdd {
+                    repo,
+                    identifier,
+                    regex,
+                    comment,
+                } => watch_add(bot, msg, repo, identifier, regex, comment).await,
+                command::Notifier::WatchRemove { repo, identifier } => {
+                    watch_remove(bot, msg, repo, identifier).await
+                }
+                command::Notifier::Mute { target, duration } => {
+                    mute(bot, msg, target, duration).await
+                }
+                command::Notifier::Unmute { target } => unmute(bot, msg, target).await,
+                command::Notifier::Delivery { delivery } => set_delivery(bot, msg, delivery).await,
+                command::Notifier::Digest {
+                    schedule,
+                    urgent_bypass,
+                    disable,
+                } => digest(bot, msg, schedule, urgent_bypass, disable).await,
+                command::Notifier::Quiet {
+                    start,
+                    end,
+                    timezone,
+                    mode,
+                    disable,
+                } => {
+                    let settings = match (start, end, timezone) {
+                        (Some(start), Some(end), Some(timezone)) => Some(QuietSettings {
+                            start,
+                            end,
+                            timezone,
+                            mode,
+                        }),
+                        _ => None,
+                    };
+                    quiet(bot, msg, settings, disable).await
+                }
+                command::Notifier::Language { language } => set_language(bot, msg, language).await,
+                command::Notifier::Template {
+                    kind,
+                    template,
+                    repo,
+                    clear,
+                } => set_template(bot, msg, kind, template, repo, clear).await,
+                command::Notifier::History { repo, limit } => history(bot, msg, repo, limit).await,
+                command::Notifier::Dashboard {
+                    enable,
+                    pin,
+                    disable,
+                } => set_dashboard(bot, msg, enable, pin, disable).await,
+                command::Notifier::RoleSet {
+                    role,
+                    user,
+                    default,
+                } => role_set(bot, msg, role, user.map(UserId), default).await,
+                command::Notifier::RoleList => role_list(bot, msg).await,
+                command::Notifier::Export { repo } => export(bot, msg, repo).await,
+                command::Notifier::Import { replace } => import(bot, msg, replace).await,
                 command::Notifier::ConditionAdd {
                     repo,
                     identifier,
@@ -293,7 +437,11 @@ async fn answer(bot: Bot, msg: Message, bc: BCommand) -> ResponseResult<()> {
                 command::Notifier::ConditionRemove { repo, identifier } => {
                     condition_remove(bot, msg, repo, identifier).await
                 }
-                command::Notifier::List => list(bot, msg).await,
+                command::Notifier::List {
+                    filter,
+                    subscribers,
+                    file,
+                } => list(bot, msg, filter, subscribers, file).await,
             }
         }
         Err(Error::Clap(e)) => {
@@ -316,6 +464,19 @@ async fn answer(bot: Bot, msg: Message, bc: BCommand) -> ResponseResult<()> {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct SubscribeTerm(String, String, String, usize);
 
+// (chat, approve)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct AccessTerm(i64, usize);
+
+// terms are distinguished by their shapes
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum CallbackTerm {
+    Subscribe(SubscribeTerm),
+    Access(AccessTerm),
+    List(ListTerm),
+}
+
 async fn handle_callback_query(bot: Bot, query: CallbackQuery) -> ResponseResult<()> {
     let result = handle_callback_query_command_result(&bot, &query).await;
     let (message, alert) = match result {
@@ -331,19 +492,99 @@ async fn handle_callback_query(bot: Bot, query: CallbackQuery) -> ResponseResult
 }
 
 async fn handle_callback_query_command_result(
-    _bot: &Bot,
+    bot: &Bot,
     query: &CallbackQuery,
 ) -> Result<String, CommandError> {
     log::debug!("query = {query:?}");
+    let data = query.data.as_ref().ok_or(Error::SubscribeCallbackNoData)?;
+    match serde_json::from_str(data).map_err(Error::Serde)? {
+        CallbackTerm::Subscribe(term) => handle_subscribe_callback(query, term).await,
+        CallbackTerm::Access(term) => handle_access_callback(bot, query, term).await,
+        CallbackTerm::List(term) => handle_list_callback(bot, query, term).await,
+    }
+}
+
+async fn handle_list_callback(
+    bot: &Bot,
+    query: &CallbackQuery,
+    term: ListTerm,
+) -> Result<String, CommandError> {
+    let (chat_id, _user) = get_chat_id_and_user_from_query(query)?;
+    let msg = query
+        .message
+        .as_ref()
+        .ok_or(Error::SubscribeCallbackNoMsgId)?;
+    let list_query = list::load(term.query).await?;
+    if list_query.chat != chat_id {
+        return Err(Error::ListExpired.into());
+    }
+    // items may have changed since the list was sent
+    let entries = list::entries(&list_query).await?;
+    let pages = list::page_count(&entries);
+    let page = term.page.min(pages - 1);
+    let footer = list::quota_markdown(chat_id).await?;
+    let result = bot
+        .edit_message_text(
+            chat_id,
+            msg.id(),
+            list::page_markdown(&entries, page, &footer),
+        )
+        .parse_mode(ParseMode::MarkdownV2)
+        .disable_link_preview(true)
+        .reply_markup(list::page_markup(term.query, page, pages)?)
+        .await;
+    match result {
+        Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(format!("page {}/{pages}", page + 1))
+}
+
+async fn handle_access_callback(
+    bot: &Bot,
+    query: &CallbackQuery,
+    term: AccessTerm,
+) -> Result<String, CommandError> {
+    let (chat_id, _user) = get_chat_id_and_user_from_query(query)?;
+    if chat_id.0 != options::get().admin_chat_id {
+        return Err(Error::NotAdminChat.into());
+    }
+    let msg = query
+        .message
+        .as_ref()
+        .ok_or(Error::SubscribeCallbackNoMsgId)?;
+    let AccessTerm(chat, approve) = term;
+    let chat = ChatId(chat);
+    let (result, notice) = if approve != 0 {
+        chat::allow(chat).await?;
+        ACCESS_REQUESTS.lock().await.remove(&chat);
+        (format!("chat {chat} allowed"), "access approved")
+    } else {
+        ACCESS_REQUESTS
+            .lock()
+            .await
+            .insert(chat, AccessRequest::Denied(Instant::now()));
+        (format!("chat {chat} denied"), "access denied")
+    };
+    if let Err(e) = bot.send_message(chat, notice).await {
+        log::warn!("failed to notify chat {chat} of access request result: {e}");
+    }
+    // the request is handled, remove the buttons
+    bot.edit_message_reply_markup(chat_id, msg.id()).await?;
+    Ok(result)
+}
+
+async fn handle_subscribe_callback(
+    query: &CallbackQuery,
+    term: SubscribeTerm,
+) -> Result<String, CommandError> {
     let (chat_id, user) = get_chat_id_and_user_from_query(query)?;
     let subscriber = Subscriber::from_tg_user(&user);
     let _msg = query
         .message
         .as_ref()
         .ok_or(Error::SubscribeCallbackNoMsgId)?;
-    let data = query.data.as_ref().ok_or(Error::SubscribeCallbackNoData)?;
-    let SubscribeTerm(kind, repo, id, subscribe) =
-        serde_json::from_str(data).map_err(Error::Serde)?;
+    let SubscribeTerm(kind, repo, id, subscribe) = term;
     let unsubscribe = subscribe == 0;
     match kind.as_str() {
         "b" => {
@@ -468,109 +709,247 @@ async fn schedule(bot: Bot) {
     }
 }
 
-async fn list(bot: Bot, msg: Message) -> Result<(), CommandError> {
+async fn list(
+    bot: Bot,
+    msg: Message,
+    filter: command::ListFilter,
+    subscribers: bool,
+    file: bool,
+) -> Result<(), CommandError> {
     let options = options::get();
     let chat = msg.chat.id;
-    if ChatId(options.admin_chat_id) == chat {
+    if ChatId(options.admin_chat_id) == chat && filter.is_empty() && !file {
         list_for_admin(bot.clone(), &msg).await?;
     }
-    list_for_normal(bot, &msg).await
+    log::info!("list for chat: {chat}");
+    let query = list::ListQuery::new(chat, filter, subscribers, msg.from.as_ref().map(|u| u.id))?;
+    let entries = list::entries(&query).await?;
+    if file || list::too_long(&entries) {
+        let file =
+            InputFile::memory(list::plain_text(&entries)).file_name(format!("list-{chat}.txt"));
+        bot.send_document(chat, file)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+    let footer = list::quota_markdown(chat).await?;
+    let pages = list::page_count(&entries);
+    let mut send = reply_to_msg(&bot, &msg, list::page_markdown(&entries, 0, &footer))
+        .parse_mode(ParseMode::MarkdownV2)
+        .disable_link_preview(true);
+    if pages > 1 {
+        let id = list::store(query).await;
+        send = send.reply_markup(list::page_markup(id, 0, pages)?);
+    }
+    send.await?;
+    Ok(())
 }
 
+const MAX_LIST_MESSAGE_LENGTH: usize = 4000;
+
 async fn list_for_admin(bot: Bot, msg: &Message) -> Result<(), CommandError> {
     log::info!("list for admin");
-    let mut result = String::new();
-
-    let repos = repo::list().await?;
-    for repo in repos {
-        result.push_str("\\- *");
-        result.push_str(&markdown::escape(&repo));
-        result.push_str("*\n");
-        let settings_json = {
+    let mut lines = Vec::new();
+    for repo in repo::list().await? {
+        let settings = {
             let resources = repo::resources(&repo).await?;
             let settings = resources.settings.read().await;
-            serde_json::to_string(&*settings).map_err(Error::Serde)?
+            settings.clone()
         };
-        result.push_str("  ");
-        result.push_str(&markdown::escape(&settings_json));
-        result.push('\n');
+        lines.push(format!("*{}*", markdown::escape(&repo)));
+        lines.push(format!(
+            "  branch regex: `{}`",
+            markdown::escape(settings.branch_regex.as_str())
+        ));
+        if let Some(info) = &settings.github_info {
+            lines.push(format!("  github: {}", markdown::escape(&info.to_string())));
+        }
+        if !settings.pipeline.is_empty() {
+            lines.push(format!(
+                "  pipeline: {}",
+                markdown::escape(&settings.pipeline.join(" → "))
+            ));
+        }
+        if !settings.conditions.is_empty() {
+            let conditions: Vec<_> = settings.conditions.keys().map(String::as_str).collect();
+            lines.push(format!(
+                "  conditions: {}",
+                markdown::escape(&conditions.join(", "))
+            ));
+        }
+        if !settings.templates.is_empty() {
+            let kinds: Vec<_> = settings
+                .templates
+                .keys()
+                .map(|kind| format!("{kind:?}"))
+                .collect();
+            lines.push(format!(
+                "  templates: {}",
+                markdown::escape(&kinds.join(", "))
+            ));
+        }
     }
-    if result.is_empty() {
-        result.push_str("(nothing)\n");
+    if lines.is_empty() {
+        lines.push("\\(nothing\\)".to_string());
     }
-    reply_to_msg(&bot, msg, result)
-        .parse_mode(ParseMode::MarkdownV2)
-        .disable_link_preview(true)
-        .await?;
+    for text in split_lines(lines, MAX_LIST_MESSAGE_LENGTH) {
+        reply_to_msg(&bot, msg, text)
+            .parse_mode(ParseMode::MarkdownV2)
+            .disable_link_preview(true)
+            .await?;
+    }
+    Ok(())
+}
 
+async fn return_chat_id(bot: Bot, msg: Message) -> Result<(), CommandError> {
+    reply_to_msg(&bot, &msg, format!("{}", msg.chat.id)).await?;
     Ok(())
 }
 
-async fn list_for_normal(bot: Bot, msg: &Message) -> Result<(), CommandError> {
-    let chat = msg.chat.id;
-    log::info!("list for chat: {chat}");
-    let mut result = String::new();
+async fn chat_allow(bot: Bot, msg: Message, chat: ChatId) -> Result<(), CommandError> {
+    ensure_admin_chat(&msg)?;
+    let reply = if chat::allow(chat).await? {
+        format!("chat {chat} allowed")
+    } else {
+        format!("chat {chat} is already allowed")
+    };
+    ACCESS_REQUESTS.lock().await.remove(&chat);
+    reply_to_msg(&bot, &msg, reply).await?;
+    Ok(())
+}
 
-    let repos = chat::repos(chat).await?;
-    for repo in repos {
-        result.push('*');
-        result.push_str(&markdown::escape(&repo));
-        result.push_str("*\n");
+async fn chat_quota(
+    bot: Bot,
+    msg: Message,
+    chat: ChatId,
+    options: command::QuotaEditOptions,
+) -> Result<(), CommandError> {
+    ensure_admin_chat(&msg)?;
+    if options.is_edit() {
+        let chat_resources = chat::chat_resources(chat).await?;
+        let mut quota = chat_resources.settings.read().await.quota.clone();
+        options.apply(&mut quota);
+        chat::chat_quota_set(chat, quota).await?;
+    }
+    let mut lines = vec![format!("quota of chat {chat}:")];
+    for (kind, used, limit) in chat::quota_usage(chat).await? {
+        lines.push(format!("- {kind}: {used}/{limit}"));
+    }
+    reply_to_msg(&bot, &msg, lines.join("\n")).await?;
+    Ok(())
+}
 
-        let resources = chat::resources_chat_repo(chat, repo).await?;
-        let settings = {
-            let locked = resources.settings.read().await;
-            locked.clone()
+async fn chat_deny(
+    bot: Bot,
+    msg: Message,
+    chat: ChatId,
+    data: command::DenyData,
+) -> Result<(), CommandError> {
+    ensure_admin_chat(&msg)?;
+    let reply = match chat::deny(chat, data == command::DenyData::Delete).await? {
+        Some(archive) => format!("chat {chat} denied, data archived to {archive:?}"),
+        None => format!("chat {chat} denied, data deleted"),
+    };
+    ACCESS_REQUESTS
+        .lock()
+        .await
+        .insert(chat, AccessRequest::Denied(Instant::now()));
+    reply_to_msg(&bot, &msg, reply).await?;
+    Ok(())
+}
+
+async fn chat_list(bot: Bot, msg: Message) -> Result<(), CommandError> {
+    ensure_admin_chat(&msg)?;
+    let mut lines = Vec::new();
+    for chat in chat::chats().await? {
+        let title = match bot.get_chat(chat).await {
+            Ok(info) => info
+                .title()
+                .map(str::to_string)
+                .or_else(|| info.username().map(|u| format!("@{u}")))
+                .unwrap_or_default(),
+            Err(e) => format!("({e})"),
         };
+        let repos = chat::repos(chat).await?;
+        lines.push(format!("{chat} {title}: {} repositories", repos.len()));
+    }
+    if lines.is_empty() {
+        lines.push("(nothing)".to_string());
+    }
+    reply_to_msg(&bot, &msg, lines.join("\n")).await?;
+    Ok(())
+}
 
-        result.push_str("  *commits*:\n");
-        let commits = &settings.commits;
-        if commits.is_empty() {
-            result.push_str("  \\(nothing\\)\n");
-        }
-        for (commit, settings) in commits {
-            result.push_str(&format!(
-                "  \\- `{}`\n    {}\n",
-                markdown::escape(commit),
-                settings.notify.description_markdown()
-            ));
-        }
-        result.push_str("  *PRs/issues*:\n");
-        let pr_issues = &settings.pr_issues;
-        if pr_issues.is_empty() {
-            result.push_str("  \\(nothing\\)\n");
-        }
-        for (id, settings) in pr_issues {
-            result.push_str(&format!(
-                "  \\- `{id}`\n    {}\n",
-                markdown::escape(settings.url.as_str())
-            ));
-        }
-        result.push_str("  *branches*:\n");
-        let branches = &settings.branches;
-        if branches.is_empty() {
-            result.push_str("  \\(nothing\\)\n");
-        }
-        for branch in branches.keys() {
-            result.push_str(&format!("  \\- `{}`\n", markdown::escape(branch)));
-        }
+#[derive(Debug, Clone, Copy)]
+enum AccessRequest {
+    // waiting for the admin
+    Pending,
+    Denied(Instant),
+}
 
-        result.push('\n');
+// denied chats can not request again for a while
+const ACCESS_REQUEST_COOLDOWN: Duration = Duration::from_secs(24 * 60 * 60);
+
+static ACCESS_REQUESTS: LazyLock<tokio::sync::Mutex<BTreeMap<ChatId, AccessRequest>>> =
+    LazyLock::new(Default::default);
+
+async fn access_request(bot: Bot, msg: Message) -> Result<(), CommandError> {
+    let chat = msg.chat.id;
+    if chat::allowed(chat) {
+        reply_to_msg(&bot, &msg, "this chat is already allowed").await?;
+        return Ok(());
+    }
+    let rejected = {
+        let mut requests = ACCESS_REQUESTS.lock().await;
+        match requests.get(&chat) {
+            Some(AccessRequest::Pending) => Some("access request is already pending"),
+            Some(AccessRequest::Denied(at)) if at.elapsed() < ACCESS_REQUEST_COOLDOWN => {
+                Some("access was denied recently, try again later")
+            }
+            _ => {
+                requests.insert(chat, AccessRequest::Pending);
+                None
+            }
+        }
+    };
+    if let Some(reply) = rejected {
+        reply_to_msg(&bot, &msg, reply).await?;
+        return Ok(());
     }
-    if result.is_empty() {
-        result.push_str("\\(nothing\\)\n");
+    let title = msg
+        .chat
+        .title()
+        .map(str::to_string)
+        .or_else(|| msg.chat.username().map(|u| format!("@{u}")))
+        .unwrap_or_default();
+    let requester = msg.from.as_ref().map(User::full_name).unwrap_or_default();
+    let markup = access_request_markup(chat)?;
+    let options = options::get();
+    let sent = bot
+        .send_message(
+            ChatId(options.admin_chat_id),
+            format!("chat {chat} {title} requests access, requested by {requester}"),
+        )
+        .reply_markup(markup)
+        .await;
+    if let Err(e) = sent {
+        ACCESS_REQUESTS.lock().await.remove(&chat);
+        return Err(e.into());
     }
-    reply_to_msg(&bot, msg, markdown::expandable_blockquote(&result))
-        .parse_mode(ParseMode::MarkdownV2)
-        .disable_link_preview(true)
-        .await?;
-
+    reply_to_msg(&bot, &msg, "access request sent to the admin").await?;
     Ok(())
 }
 
-async fn return_chat_id(bot: Bot, msg: Message) -> Result<(), CommandError> {
-    reply_to_msg(&bot, &msg, format!("{}", msg.chat.id)).await?;
-    Ok(())
+fn access_request_markup(chat: ChatId) -> Result<InlineKeyboardMarkup, Error> {
+    let approve_data = serde_json::to_string(&AccessTerm(chat.0, 1))?;
+    let deny_data = serde_json::to_string(&AccessTerm(chat.0, 0))?;
+    let approve_button = InlineKeyboardButton::new(
+        "Approve",
+        InlineKeyboardButtonKind::CallbackData(approve_data),
+    );
+    let deny_button =
+        InlineKeyboardButton::new("Deny", InlineKeyboardButtonKind::CallbackData(deny_data));
+    Ok(InlineKeyboardMarkup::new([[approve_button, deny_button]]))
 }
 
 async fn repo_add(bot: Bot, msg: Message, name: String, url: String) -> Result<(), CommandError> {
@@ -622,24 +1001,13 @@ async fn repo_edit(
     bot: Bot,
     msg: Message,
     name: String,
-    branch_regex: Option<String>,
-    github_info: Option<GitHubInfo>,
-    clear_github_info: bool,
+    options: command::RepoEditOptions,
 ) -> Result<(), CommandError> {
     ensure_admin_chat(&msg)?;
     let resources = repo::resources(&name).await?;
     let new_settings = {
         let mut locked = resources.settings.write().await;
-        if let Some(r) = branch_regex {
-            let regex = Regex::new(&format!("^({r})$")).map_err(Error::from)?;
-            locked.branch_regex = regex;
-        }
-        if let Some(info) = github_info {
-            locked.github_info = Some(info);
-        }
-        if clear_github_info {
-            locked.github_info = None;
-        }
+        options.apply(&mut locked)?;
         locked.clone()
     };
     resources.save_settings().await?;
@@ -669,8 +1037,9 @@ async fn condition_add(
 ) -> Result<(), CommandError> {
     ensure_admin_chat(&msg)?;
     let resources = repo::resources(&repo).await?;
+    let pipeline = resources.settings.read().await.pipeline.clone();
     let settings = ConditionSettings {
-        condition: GeneralCondition::parse(kind, &expr)?,
+        condition: GeneralCondition::parse(kind, &expr, &pipeline)?,
     };
     repo::condition_add(&resources, &identifier, settings).await?;
     reply_to_msg(&bot, &msg, format!("condition {identifier} added")).await?;
@@ -697,15 +1066,20 @@ async fn commit_add(
     hash: String,
     comment: String,
     url: Option<Url>,
+    living: bool,
 ) -> Result<(), CommandError> {
     let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+    let repo_resources = repo::resources(&repo).await?;
+    let hash = repo::resolve_commit(repo_resources.clone(), &hash).await?;
     let guard = resources.commit_lock(hash.clone()).await;
     let subscribers = subscriber_from_msg(&msg).into_iter().collect();
     let settings = CommitSettings {
         url,
+        living,
         notify: NotifySettings {
             comment,
             subscribers,
+            mute: None,
         },
     };
     match chat::commit_add(&resources, &hash, settings).await {
@@ -745,6 +1119,36 @@ async fn commit_remove(
     Ok(())
 }
 
+async fn commit_edit(
+    bot: Bot,
+    msg: Message,
+    repo: String,
+    hash: String,
+    notify: command::NotifyEdit,
+    options: command::CommitEditOptions,
+) -> Result<(), CommandError> {
+    let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+    let _guard = resources.commit_lock(hash.clone()).await;
+    let new_settings = {
+        let mut locked = resources.settings.write().await;
+        let settings = locked
+            .commits
+            .get_mut(&hash)
+            .ok_or_else(|| Error::UnknownCommit(hash.clone()))?;
+        notify.apply(&mut settings.notify);
+        options.apply(settings);
+        settings.clone()
+    };
+    resources.save_settings().await?;
+    reply_to_msg(
+        &bot,
+        &msg,
+        format!("commit {hash} edited, current settings:\n{new_settings:#?}"),
+    )
+    .await?;
+    Ok(())
+}
+
 async fn commit_check(
     bot: Bot,
     msg: Message,
@@ -764,7 +1168,8 @@ async fn commit_check(
     };
     let result = chat::commit_check(&resources, &repo_resources, &hash).await?;
     // do not mention in manual check
-    let reply = commit_check_message(&repo, &hash, &commit_settings, &result, false);
+    let lang = chat::language(msg.chat.id).await;
+    let reply = commit_check_message(lang, &repo, &hash, &commit_settings, &result, false);
     let mut send = reply_to_msg(&bot, &msg, reply)
         .parse_mode(ParseMode::MarkdownV2)
         .disable_link_preview(true);
@@ -819,6 +1224,7 @@ async fn pr_issue_add(
         notify: NotifySettings {
             comment,
             subscribers,
+            mute: None,
         },
     };
     match chat::pr_issue_add(&resources, &repo_resources, id, settings).await {
@@ -840,22 +1246,56 @@ async fn pr_issue_add(
     }
 }
 
+async fn pr_issue_edit(
+    bot: Bot,
+    msg: Message,
+    repo: String,
+    id: u64,
+    notify: command::NotifyEdit,
+    url: Option<Url>,
+) -> Result<(), CommandError> {
+    let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+    let new_settings = {
+        let mut locked = resources.settings.write().await;
+        let settings = locked
+            .pr_issues
+            .get_mut(&id)
+            .ok_or_else(|| Error::UnknownPRIssue(id))?;
+        notify.apply(&mut settings.notify);
+        if let Some(url) = url {
+            settings.url = url;
+        }
+        settings.clone()
+    };
+    resources.save_settings().await?;
+    reply_to_msg(
+        &bot,
+        &msg,
+        format!("PR/issue {repo}/{id} edited, current settings:\n{new_settings:#?}"),
+    )
+    .await?;
+    Ok(())
+}
+
 async fn pr_issue_check(bot: Bot, msg: Message, repo: String, id: u64) -> Result<(), CommandError> {
     let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
     let repo_resources = repo::resources(&repo).await?;
     match chat::pr_issue_check(&resources, &repo_resources, id).await {
         Ok(result) => {
             let pretty_id = pr_issue_id_pretty(&repo_resources, id).await?;
+            let lang = chat::language(msg.chat.id).await;
             match result {
                 PRIssueCheckResult::Merged(commit) => commit_check(bot, msg, repo, commit).await,
                 PRIssueCheckResult::Closed => {
-                    reply_to_msg(&bot, &msg, format!("{pretty_id} has been closed"))
+                    let reply = lang.fill(Key::PRIssueClosed, &[("id", &pretty_id)]);
+                    reply_to_msg(&bot, &msg, reply)
                         .parse_mode(ParseMode::MarkdownV2)
                         .await?;
                     Ok(())
                 }
                 PRIssueCheckResult::Opened => {
-                    reply_to_msg(&bot, &msg, format!("{pretty_id} has been opened"))
+                    let reply = lang.fill(Key::PRIssueOpened, &[("id", &pretty_id)]);
+                    reply_to_msg(&bot, &msg, reply)
                         .parse_mode(ParseMode::MarkdownV2)
                         .await?;
                     Ok(())
@@ -962,12 +1402,10 @@ async fn branch_add(
     msg: Message,
     repo: String,
     branch: String,
+    settings: BranchSettings,
 ) -> Result<(), CommandError> {
     let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
     let guard = resources.branch_lock(branch.clone()).await;
-    let settings = BranchSettings {
-        notify: Default::default(),
-    };
     match chat::branch_add(&resources, &branch, settings).await {
         Ok(()) => {
             drop(guard);
@@ -996,6 +1434,36 @@ async fn branch_remove(
     Ok(())
 }
 
+async fn branch_edit(
+    bot: Bot,
+    msg: Message,
+    repo: String,
+    branch: String,
+    notify: command::NotifyEdit,
+    options: command::BranchEditOptions,
+) -> Result<(), CommandError> {
+    let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+    let _guard = resources.branch_lock(branch.clone()).await;
+    let new_settings = {
+        let mut locked = resources.settings.write().await;
+        let settings = locked
+            .branches
+            .get_mut(&branch)
+            .ok_or_else(|| Error::UnknownBranch(branch.clone()))?;
+        notify.apply(&mut settings.notify);
+        options.apply(settings);
+        settings.clone()
+    };
+    resources.save_settings().await?;
+    reply_to_msg(
+        &bot,
+        &msg,
+        format!("branch {repo}/{branch} edited, current settings:\n{new_settings:#?}"),
+    )
+    .await?;
+    Ok(())
+}
+
 async fn branch_check(
     bot: Bot,
     msg: Message,
@@ -1014,9 +1482,11 @@ async fn branch_check(
             .clone()
     };
     let result = chat::branch_check(&resources, &repo_resources, &branch).await?;
+    let lang = chat::language(msg.chat.id).await;
     let reply = {
         let settings = repo_resources.settings.read().await;
         branch_check_message(
+            lang,
             &repo,
             &branch,
             &branch_settings,
@@ -1056,6 +1526,564 @@ async fn branch_subscribe(
     Ok(())
 }
 
+async fn branch_pair_add(
+    bot: Bot,
+    msg: Message,
+    repo: String,
+    branch: String,
+    base: String,
+    threshold: Option<usize>,
+) -> Result<(), CommandError> {
+    let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+    let repo_resources = repo::resources(&repo).await?;
+    // ensure both branches exist
+    let result = chat::branch_compare(&repo_resources, &branch, &base).await?;
+    let subscribers = subscriber_from_msg(&msg).into_iter().collect();
+    let settings = BranchPairSettings {
+        branch: branch.clone(),
+        base: base.clone(),
+        threshold,
+        notify: NotifySettings {
+            comment: Default::default(),
+            subscribers,
+            mute: None,
+        },
+    };
+    chat::branch_pair_add(&resources, settings).await?;
+    let lang = chat::language(msg.chat.id).await;
+    reply_to_msg(
+        &bot,
+        &msg,
+        branch_compare_message(lang, &repo, &branch, &base, &result),
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .await?;
+    Ok(())
+}
+
+async fn branch_pair_remove(
+    bot: Bot,
+    msg: Message,
+    repo: String,
+    branch: String,
+    base: String,
+) -> Result<(), CommandError> {
+    let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+    let key = BranchPairSettings::key(&branch, &base);
+    chat::branch_pair_remove(&resources, &key).await?;
+    reply_to_msg(&bot, &msg, format!("branch pair {repo}/{key} removed")).await?;
+    Ok(())
+}
+
+async fn branch_compare(
+    bot: Bot,
+    msg: Message,
+    repo: String,
+    branch: String,
+    base: String,
+) -> Result<(), CommandError> {
+    // ensure the chat is in allow list
+    let _resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+    let repo_resources = repo::resources(&repo).await?;
+    let result = chat::branch_compare(&repo_resources, &branch, &base).await?;
+    let lang = chat::language(msg.chat.id).await;
+    reply_to_msg(
+        &bot,
+        &msg,
+        branch_compare_message(lang, &repo, &branch, &base, &result),
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .await?;
+    Ok(())
+}
+
+async fn watch_add(
+    bot: Bot,
+    msg: Message,
+    repo: String,
+    identifier: String,
+    regex: String,
+    optional_comment: Option<String>,
+) -> Result<(), CommandError> {
+    let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+    let message_regex = Regex::new(&regex).map_err(Error::from)?;
+    let subscribers = subscriber_from_msg(&msg).into_iter().collect();
+    let comment = optional_comment.unwrap_or_default();
+    let settings = WatchSettings {
+        message_regex,
+        notify: NotifySettings {
+            comment,
+            subscribers,
+            mute: None,
+        },
+    };
+    chat::watch_add(&resources, &identifier, settings).await?;
+    reply_to_msg(&bot, &msg, format!("watch rule {identifier} added")).await?;
+    Ok(())
+}
+
+async fn watch_remove(
+    bot: Bot,
+    msg: Message,
+    repo: String,
+    identifier: String,
+) -> Result<(), CommandError> {
+    let resources = chat::resources_msg_repo(&msg, repo).await?;
+    chat::watch_remove(&resources, &identifier).await?;
+    reply_to_msg(&bot, &msg, format!("watch rule {identifier} removed")).await?;
+    Ok(())
+}
+
+async fn mute(
+    bot: Bot,
+    msg: Message,
+    target: command::ItemTarget,
+    duration: Option<humantime::Duration>,
+) -> Result<(), CommandError> {
+    let until = duration.map(|d| Utc::now() + Duration::from(d));
+    let target = match target.item()? {
+        Some((repo, item)) => {
+            let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+            chat::item_mute(&resources, &item, until).await?;
+            format!("[{repo}] {item}")
+        }
+        None => {
+            let chat_resources = chat::chat_resources(msg.chat.id).await?;
+            chat::chat_mute(&chat_resources, until).await?;
+            "this chat".to_string()
+        }
+    };
+    let reply = match until {
+        Some(until) => format!("{target} muted until {until}"),
+        None => format!("{target} muted"),
+    };
+    reply_to_msg(&bot, &msg, reply).await?;
+    Ok(())
+}
+
+async fn unmute(bot: Bot, msg: Message, target: command::ItemTarget) -> Result<(), CommandError> {
+    let (target, mute) = match target.item()? {
+        Some((repo, item)) => {
+            let resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+            let mute = chat::item_unmute(&resources, &item).await?;
+            (format!("[{repo}] {item}"), mute)
+        }
+        None => {
+            let chat_resources = chat::chat_resources(msg.chat.id).await?;
+            let mute = chat::chat_unmute(&chat_resources).await?;
+            (String::new(), mute)
+        }
+    };
+    let lang = chat::language(msg.chat.id).await;
+    let target = if target.is_empty() {
+        lang.text(Key::MuteTargetChat).to_string()
+    } else {
+        markdown::escape(&target)
+    };
+    let message = mute_summary_message(lang, &target, &mute, false);
+    reply_to_msg(&bot, &msg, message)
+        .parse_mode(ParseMode::MarkdownV2)
+        .await?;
+    Ok(())
+}
+
+async fn set_delivery(
+    bot: Bot,
+    msg: Message,
+    delivery: Option<Delivery>,
+) -> Result<(), CommandError> {
+    let user = msg.from.as_ref().ok_or(Error::NoSubscriber)?;
+    let chat_resources = chat::chat_resources(msg.chat.id).await?;
+    let current = match delivery {
+        Some(delivery) => {
+            {
+                let mut settings = chat_resources.settings.write().await;
+                if delivery == Delivery::Mention {
+                    settings.delivery.remove(&user.id);
+                } else {
+                    settings.delivery.insert(user.id, delivery);
+                }
+            }
+            chat_resources.save_settings().await?;
+            delivery
+        }
+        None => {
+            let settings = chat_resources.settings.read().await;
+            settings.delivery.get(&user.id).copied().unwrap_or_default()
+        }
+    };
+    let mut reply = format!("delivery for {}: {current:?}", user.full_name());
+    if current != Delivery::Mention {
+        reply.push_str(
+            "\nprivate messages only work after you have started a conversation with the bot",
+        );
+    }
+    reply_to_msg(&bot, &msg, reply).await?;
+    Ok(())
+}
+
+async fn digest(
+    bot: Bot,
+    msg: Message,
+    schedule: Option<String>,
+    urgent_bypass: bool,
+    disable: bool,
+) -> Result<(), CommandError> {
+    let chat_resources = chat::chat_resources(msg.chat.id).await?;
+    if disable {
+        let pending = chat::digest_disable(&chat_resources).await?;
+        reply_to_msg(&bot, &msg, "digest disabled").await?;
+        if let Some((since, events)) = pending {
+            notify::send_digest(&bot, msg.chat.id, since, &events, false).await?;
+        }
+        return Ok(());
+    }
+    if let Some(schedule) = schedule {
+        let settings = DigestSettings {
+            schedule,
+            urgent_bypass,
+        };
+        chat::digest_set(&chat_resources, settings).await?;
+    }
+    let reply = {
+        let settings = chat_resources.settings.read().await;
+        let results = chat_resources.results.read().await;
+        match &settings.digest {
+            Some(digest) => {
+                let next = Schedule::from_str(&digest.schedule)
+                    .map_err(Error::from)?
+                    .upcoming(Utc)
+                    .next();
+                format!(
+                    "digest enabled, schedule: {schedule}, urgent bypass: {bypass}, next digest: {next}, queued events: {queued}",
+                    schedule = digest.schedule,
+                    bypass = digest.urgent_bypass,
+                    next = next
+                        .map(|t| t.to_string())
+                        .unwrap_or_else(|| "never".to_string()),
+                    queued = results.digest.events.len(),
+                )
+            }
+            None => "digest disabled".to_string(),
+        }
+    };
+    reply_to_msg(&bot, &msg, reply).await?;
+    Ok(())
+}
+
+async fn quiet(
+    bot: Bot,
+    msg: Message,
+    settings: Option<QuietSettings>,
+    disable: bool,
+) -> Result<(), CommandError> {
+    let chat_resources = chat::chat_resources(msg.chat.id).await?;
+    if disable {
+        chat::quiet_set(&chat_resources, None).await?;
+    } else if settings.is_some() {
+        chat::quiet_set(&chat_resources, settings).await?;
+    }
+    let reply = {
+        let settings = chat_resources.settings.read().await;
+        match &settings.quiet {
+            Some(quiet) => format!(
+                "quiet hours: {start} - {end} ({timezone}), mode: {mode:?}",
+                start = quiet.start.format("%H:%M"),
+                end = quiet.end.format("%H:%M"),
+                timezone = quiet.timezone,
+                mode = quiet.mode,
+            ),
+            None => "quiet hours disabled".to_string(),
+        }
+    };
+    reply_to_msg(&bot, &msg, reply).await?;
+    Ok(())
+}
+
+async fn set_language(
+    bot: Bot,
+    msg: Message,
+    language: Option<Language>,
+) -> Result<(), CommandError> {
+    let chat_resources = chat::chat_resources(msg.chat.id).await?;
+    if let Some(language) = language {
+        chat::language_set(&chat_resources, language).await?;
+    }
+    let current = chat_resources.settings.read().await.language;
+    reply_to_msg(&bot, &msg, format!("language: {current:?}")).await?;
+    Ok(())
+}
+
+async fn role_set(
+    bot: Bot,
+    msg: Message,
+    role: Option<Role>,
+    user: Option<UserId>,
+    default: bool,
+) -> Result<(), CommandError> {
+    let chat_resources = chat::chat_resources(msg.chat.id).await?;
+    if default {
+        // clap ensures the role is given
+        let role = role.unwrap_or_default();
+        chat::default_role_set(&chat_resources, role).await?;
+        reply_to_msg(&bot, &msg, format!("default role: {role:?}")).await?;
+        return Ok(());
+    }
+    let user = match user {
+        Some(user) => user,
+        None => msg
+            .reply_to_message()
+            .and_then(|m| m.from.as_ref())
+            .map(|u| u.id)
+            .ok_or(Error::NoRoleTarget)?,
+    };
+    chat::role_set(&chat_resources, user, role).await?;
+    let current = chat_resources.settings.read().await.role(user);
+    reply_to_msg(&bot, &msg, format!("role of user {user}: {current:?}")).await?;
+    Ok(())
+}
+
+async fn role_list(bot: Bot, msg: Message) -> Result<(), CommandError> {
+    let chat_resources = chat::chat_resources(msg.chat.id).await?;
+    let (default_role, roles) = {
+        let settings = chat_resources.settings.read().await;
+        (settings.default_role, settings.roles.clone())
+    };
+    let mut lines = vec![
+        format!("default role: {default_role:?}"),
+        "chat administrators are always owners".to_string(),
+    ];
+    for (user, role) in roles {
+        let name = match bot.get_chat_member(msg.chat.id, user).await {
+            Ok(member) => member.user.full_name(),
+            Err(e) => {
+                log::debug!("failed to get member {user} of {}: {e}", msg.chat.id);
+                user.to_string()
+            }
+        };
+        lines.push(format!("- {name} ({user}): {role:?}"));
+    }
+    reply_to_msg(&bot, &msg, lines.join("\n")).await?;
+    Ok(())
+}
+
+async fn set_template(
+    bot: Bot,
+    msg: Message,
+    kind: TemplateKind,
+    template: Option<String>,
+    repo: Option<String>,
+    clear: bool,
+) -> Result<(), CommandError> {
+    if let Some(t) = &template {
+        template::validate(kind, t)?;
+    }
+    let edit = template.is_some() || clear;
+    let current = match &repo {
+        Some(repo) => {
+            if edit {
+                ensure_admin_chat(&msg)?;
+            } else {
+                // ensure the chat is in allow list
+                let _resources = chat::resources_msg_repo(&msg, repo.clone()).await?;
+            }
+            let resources = repo::resources(repo).await?;
+            if edit {
+                {
+                    let mut settings = resources.settings.write().await;
+                    match template {
+                        Some(t) => settings.templates.insert(kind, t),
+                        None => settings.templates.remove(&kind),
+                    };
+                }
+                resources.save_settings().await?;
+            }
+            resources
+                .settings
+                .read()
+                .await
+                .templates
+                .get(&kind)
+                .cloned()
+        }
+        None => {
+            let chat_resources = chat::chat_resources(msg.chat.id).await?;
+            if edit {
+                chat::template_set(&chat_resources, kind, template).await?;
+            }
+            let settings = chat_resources.settings.read().await;
+            settings.templates.get(&kind).cloned()
+        }
+    };
+    reply_to_msg(
+        &bot,
+        &msg,
+        format!(
+            "{kind:?} template: {current}\navailable placeholders: {placeholders}",
+            current = current.as_deref().unwrap_or("(default)"),
+            placeholders = kind.placeholders().join(", "),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn set_dashboard(
+    bot: Bot,
+    msg: Message,
+    enable: bool,
+    pin: bool,
+    disable: bool,
+) -> Result<(), CommandError> {
+    let chat = msg.chat.id;
+    let chat_resources = chat::chat_resources(chat).await?;
+    if disable {
+        let messages = chat::dashboard_set(&chat_resources, None).await?;
+        dashboard::delete(&bot, chat, &messages).await;
+    }
+    if enable {
+        chat::dashboard_set(&chat_resources, Some(DashboardSettings { pin })).await?;
+        dashboard::refresh(&bot, chat).await?;
+    }
+    let reply = match &chat_resources.settings.read().await.dashboard {
+        Some(dashboard) => format!("dashboard enabled, pin: {}", dashboard.pin),
+        None => "dashboard disabled".to_string(),
+    };
+    reply_to_msg(&bot, &msg, reply).await?;
+    Ok(())
+}
+
+// leave some room below the telegram limit of 4096
+const MAX_HISTORY_MESSAGE_LENGTH: usize = 4000;
+
+async fn history(
+    bot: Bot,
+    msg: Message,
+    repo: Option<String>,
+    limit: u16,
+) -> Result<(), CommandError> {
+    let chat_resources = chat::chat_resources(msg.chat.id).await?;
+    let events = chat::history(&chat_resources, repo.as_deref(), limit.into()).await;
+    let mut lines = Vec::new();
+    let mut length = 0;
+    // keep the newest events if the message is too long
+    for event in events.iter().rev() {
+        let line = format!(
+            "`{time}` {repo}_{kind}_: {summary}",
+            time = markdown::escape(&time_pretty(event.time)),
+            repo = event
+                .repo
+                .as_ref()
+                .map(|r| format!("\\[{}\\] ", markdown::escape(r)))
+                .unwrap_or_default(),
+            kind = event.kind,
+            summary = event.summary,
+        );
+        length += line.chars().count() + 1;
+        if length > MAX_HISTORY_MESSAGE_LENGTH {
+            break;
+        }
+        lines.push(line);
+    }
+    lines.reverse();
+    let result = if lines.is_empty() {
+        "\\(nothing\\)".to_string()
+    } else {
+        lines.join("\n")
+    };
+    reply_to_msg(&bot, &msg, result)
+        .parse_mode(ParseMode::MarkdownV2)
+        .disable_link_preview(true)
+        .await?;
+    Ok(())
+}
+
+async fn export(bot: Bot, msg: Message, repo: Option<String>) -> Result<(), CommandError> {
+    let chat = msg.chat.id;
+    let exported = chat::export(chat, repo).await?;
+    let json = serde_json::to_vec_pretty(&exported).map_err(Error::from)?;
+    let file = InputFile::memory(json).file_name(format!("notifier-{chat}.json"));
+    bot.send_document(chat, file)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+    Ok(())
+}
+
+async fn import(bot: Bot, msg: Message, replace: bool) -> Result<(), CommandError> {
+    let chat = msg.chat.id;
+    // the command may be the caption of the document itself
+    let document = msg
+        .document()
+        .or_else(|| msg.reply_to_message().and_then(Message::document))
+        .ok_or(Error::ImportNoDocument)?;
+    let file = bot.get_file(document.file.id.clone()).await?;
+    let mut content = Vec::new();
+    bot.download_file(&file.path, &mut content)
+        .await
+        .map_err(Error::from)?;
+    let imported: BTreeMap<String, ChatRepoSettings> =
+        serde_json::from_slice(&content).map_err(Error::from)?;
+
+    // validate everything before importing anything
+    let known_repos = repo::list().await?;
+    let mut invalid = Vec::new();
+    for (repo, settings) in &imported {
+        if !known_repos.contains(repo) {
+            invalid.push(Error::UnknownRepository(repo.clone()).to_string());
+            continue;
+        }
+        let repo_resources = repo::resources(repo).await?;
+        invalid.extend(chat::import_validate(&repo_resources, settings).await?);
+    }
+    if !invalid.is_empty() {
+        return Err(Error::ImportInvalid(invalid).into());
+    }
+
+    let mut lines = Vec::new();
+    for (repo, report) in chat::import(chat, imported, replace).await? {
+        lines.push(format!("[{repo}]"));
+        for (action, items) in [
+            ("added", report.added),
+            ("updated", report.updated),
+            ("removed", report.removed),
+        ] {
+            if !items.is_empty() {
+                lines.push(format!("  {action}: {}", items.join(", ")));
+            }
+        }
+    }
+    if lines.is_empty() {
+        lines.push("nothing imported".to_string());
+    }
+    reply_to_msg(&bot, &msg, lines.join("\n")).await?;
+    Ok(())
+}
+
+// private chats and the admin chat are not restricted
+async fn ensure_role(bot: &Bot, msg: &Message, required: Role) -> Result<(), CommandError> {
+    if required == Role::Viewer || msg.chat.is_private() || ensure_admin_chat(msg).is_ok() {
+        return Ok(());
+    }
+    // anonymous administrators send as the chat itself
+    if msg.sender_chat.as_ref().map(|c| c.id) == Some(msg.chat.id) {
+        return Ok(());
+    }
+    let user = msg.from.as_ref().ok_or(Error::NoSubscriber)?;
+    let chat_resources = chat::chat_resources(msg.chat.id).await?;
+    let actual = chat_resources.settings.read().await.role(user.id);
+    if actual >= required {
+        return Ok(());
+    }
+    if bot
+        .get_chat_member(msg.chat.id, user.id)
+        .await?
+        .is_privileged()
+    {
+        return Ok(());
+    }
+    Err(Error::PermissionDenied { required, actual }.into())
+}
+
 fn ensure_admin_chat(msg: &Message) -> Result<(), CommandError> {
     let options = options::get();
     if msg.chat_id().map(|id| id.0) == Some(options.admin_chat_id) {
@@ -1,20 +1,36 @@
-use std::{collections::BTreeSet, fmt};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    time::Duration,
+};
 
+use chrono::{DateTime, Utc};
 use teloxide::{types::Message, utils::markdown};
 
 use crate::{
     chat::{
-        results::{BranchCheckResult, CommitCheckResult},
-        settings::{BranchSettings, CommitSettings, PRIssueSettings, Subscriber},
+        MAX_BRANCH_COMMITS,
+        results::{
+            BranchChange, BranchCheckResult, BranchPairCheckResult, BranchPairEvent,
+            BranchPairResults, BranchResults, BranchStale, ChatRepoResults, CommitCheckResult,
+            CommitResults, DigestEvent, PipelineProgress, WatchCheckResult,
+        },
+        settings::{
+            BranchPairSettings, BranchSettings, ChatRepoSettings, CommitSettings, MuteSettings,
+            PRIssueSettings, Subscriber,
+        },
     },
     condition::Action,
     error::Error,
     github::GitHubInfo,
+    i18n::{Key, Language},
     repo::{pr_issue_url, resources::RepoResources},
-    utils::empty_or_start_new_line,
+    template::TemplateValues,
+    utils::{empty_or_start_new_line, first_line},
 };
 
 pub fn commit_check_message(
+    lang: Language,
     repo: &str,
     commit: &str,
     settings: &CommitSettings,
@@ -22,11 +38,16 @@ pub fn commit_check_message(
     mention: bool,
 ) -> String {
     format!(
-        "{summary}
+        "{summary}{progress}
 {details}",
         summary = commit_check_message_summary(repo, settings, result),
+        progress = result
+            .pipeline
+            .as_ref()
+            .map(|p| format!("\n{}", pipeline_progress_markdown(p)))
+            .unwrap_or_default(),
         details = markdown::expandable_blockquote(&commit_check_message_additional(
-            commit, settings, result, mention
+            lang, commit, settings, result, mention
         )),
     )
 }
@@ -48,7 +69,104 @@ pub fn commit_check_message_summary(
     )
 }
 
+// summaries are plain text, escaped by `Notification::new`
+pub fn commit_check_summary(
+    repo: &str,
+    settings: &CommitSettings,
+    result: &CommitCheckResult,
+) -> String {
+    format!(
+        "[{repo}] {comment} +{new}",
+        comment = first_line(&settings.notify.comment),
+        new = result.new.iter().cloned().collect::<Vec<_>>().join(" "),
+    )
+}
+
+pub fn pipeline_progress_markdown(progress: &PipelineProgress) -> String {
+    let bar: String = (0..progress.stages.len())
+        .map(|i| {
+            if i < progress.reached {
+                '\u{25cf}'
+            } else {
+                '\u{25cb}'
+            }
+        })
+        .collect();
+    let total = progress.stages.len();
+    let mut result = match progress.current() {
+        Some(stage) => format!(
+            "{bar} {reached}/{total}: `{stage}`",
+            reached = progress.reached,
+            stage = markdown::escape(stage),
+        ),
+        None => format!("{bar} 0/{total}"),
+    };
+    if !progress.etas.is_empty() {
+        let etas: Vec<_> = progress
+            .etas
+            .iter()
+            .map(|(stage, time)| {
+                format!(
+                    "`{stage}` {eta}",
+                    stage = markdown::escape(stage),
+                    eta = markdown::escape(&time_pretty(*time)),
+                )
+            })
+            .collect();
+        result.push_str(&format!("\n*ETA*: {}", etas.join(", ")));
+    }
+    result
+}
+
+// absolute, notifications may be delivered long after rendering
+pub fn time_pretty(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+pub fn commit_template_values(
+    lang: Language,
+    repo: &str,
+    commit: &str,
+    settings: &CommitSettings,
+    result: &CommitCheckResult,
+) -> TemplateValues {
+    let remove_conditions = result.conditions_of_action(Action::Remove);
+    vec![
+        ("repo", markdown::escape(repo)),
+        ("comment", settings.notify.description_markdown()),
+        (
+            "url",
+            settings
+                .url
+                .as_ref()
+                .map(|url| markdown::escape(url.as_str()))
+                .unwrap_or_default(),
+        ),
+        ("commit", markdown::code_inline(&markdown::escape(commit))),
+        ("new", markdown_list_compat(result.new.iter())),
+        ("all", markdown_list_compat(result.all.iter())),
+        (
+            "progress",
+            result
+                .pipeline
+                .as_ref()
+                .map(pipeline_progress_markdown)
+                .unwrap_or_default(),
+        ),
+        ("subscribers", settings.notify.subscribers_markdown(lang)),
+        (
+            "conditions",
+            if remove_conditions.is_empty() {
+                "".to_string()
+            } else {
+                markdown_list_compat(remove_conditions.iter())
+            },
+        ),
+    ]
+}
+
 pub fn commit_check_message_additional(
+    lang: Language,
     commit: &str,
     settings: &CommitSettings,
     result: &CommitCheckResult,
@@ -59,28 +177,75 @@ pub fn commit_check_message_additional(
         "".to_string()
     } else {
         format!(
-            "\n*auto removed* by conditions:
+            "\n{}
 {}",
+            lang.text(Key::CommitAutoRemoved),
             markdown_list(remove_conditions.iter())
         )
     };
     format!(
         "`{commit}`{notify}
 
-*all* branches containing this commit:
+{all_title}
 {all}
 {auto_remove_msg}
 ",
         commit = markdown::escape(commit),
         notify = if mention {
-            empty_or_start_new_line(&settings.notify.subscribers_markdown())
+            empty_or_start_new_line(&settings.notify.subscribers_markdown(lang))
         } else {
             "".to_string()
         },
+        all_title = lang.text(Key::CommitAllBranches),
         all = markdown_list(result.all.iter())
     )
 }
 
+pub fn watch_check_message(lang: Language, repo: &str, result: &WatchCheckResult) -> String {
+    format!(
+        "\\[{repo}\\] {comment}
+{started}{notify}",
+        repo = markdown::escape(repo),
+        comment = markdown::escape(&result.settings.notify.comment),
+        started = lang.fill(
+            Key::WatchStarted,
+            &[
+                ("commit", &markdown::escape(&result.commit)),
+                ("watch", &markdown::escape(&result.watch)),
+            ]
+        ),
+        notify = empty_or_start_new_line(&result.settings.notify.subscribers_markdown(lang)),
+    )
+}
+
+pub fn watch_check_summary(repo: &str, result: &WatchCheckResult) -> String {
+    format!(
+        "[{repo}] {comment} watched by {watch}",
+        comment = first_line(&result.settings.notify.comment),
+        watch = result.watch,
+    )
+}
+
+pub fn watch_template_values(
+    lang: Language,
+    repo: &str,
+    result: &WatchCheckResult,
+) -> TemplateValues {
+    vec![
+        ("repo", markdown::escape(repo)),
+        ("watch", markdown::escape(&result.watch)),
+        ("comment", result.settings.notify.description_markdown()),
+        (
+            "commit",
+            markdown::code_inline(&markdown::escape(&result.commit)),
+        ),
+        (
+            "subscribers",
+            result.settings.notify.subscribers_markdown(lang),
+        ),
+    ]
+}
+
 pub async fn pr_issue_id_pretty(resources: &RepoResources, id: u64) -> Result<String, Error> {
     let url = pr_issue_url(resources, id).await?;
     Ok(markdown::link(
@@ -89,44 +254,85 @@ pub async fn pr_issue_id_pretty(resources: &RepoResources, id: u64) -> Result<St
     ))
 }
 
+// `status` is one of "opened", "merged" and "closed", `commit` is only known when merged
+pub async fn pr_issue_template_values(
+    lang: Language,
+    resources: &RepoResources,
+    id: u64,
+    settings: &PRIssueSettings,
+    status: &str,
+    commit: Option<&str>,
+) -> Result<TemplateValues, Error> {
+    Ok(vec![
+        ("repo", markdown::escape(&resources.name)),
+        ("id", pr_issue_id_pretty(resources, id).await?),
+        ("url", markdown::escape(settings.url.as_str())),
+        ("comment", settings.notify.description_markdown()),
+        ("status", markdown::escape(status)),
+        (
+            "commit",
+            commit
+                .map(|c| markdown::code_inline(&markdown::escape(c)))
+                .unwrap_or_default(),
+        ),
+        ("subscribers", settings.notify.subscribers_markdown(lang)),
+    ])
+}
+
 pub async fn pr_issue_opened_message(
+    lang: Language,
     resources: &RepoResources,
     id: u64,
     settings: &PRIssueSettings,
 ) -> Result<String, Error> {
     Ok(format!(
-        "{pretty_id} has been opened{notify}",
-        pretty_id = pr_issue_id_pretty(resources, id).await?,
-        notify = empty_or_start_new_line(&settings.notify.subscribers_markdown()),
+        "{opened}{notify}",
+        opened = lang.fill(
+            Key::PRIssueOpened,
+            &[("id", &pr_issue_id_pretty(resources, id).await?)]
+        ),
+        notify = empty_or_start_new_line(&settings.notify.subscribers_markdown(lang)),
     ))
 }
 
 pub async fn pr_issue_merged_message(
+    lang: Language,
     resources: &RepoResources,
     id: u64,
     settings: &PRIssueSettings,
-    commit: &String,
+    commit: &str,
 ) -> Result<String, Error> {
     Ok(format!(
-        "{pretty_id} merged as `{commit}`{notify}",
-        pretty_id = pr_issue_id_pretty(resources, id).await?,
-        notify = empty_or_start_new_line(&settings.notify.subscribers_markdown()),
+        "{merged}{notify}",
+        merged = lang.fill(
+            Key::PRIssueMerged,
+            &[
+                ("id", &pr_issue_id_pretty(resources, id).await?),
+                ("commit", commit)
+            ]
+        ),
+        notify = empty_or_start_new_line(&settings.notify.subscribers_markdown(lang)),
     ))
 }
 
 pub async fn pr_issue_closed_message(
+    lang: Language,
     resources: &RepoResources,
     id: u64,
     settings: &PRIssueSettings,
 ) -> Result<String, Error> {
     Ok(format!(
-        "{pretty_id} has been closed{notify}",
-        pretty_id = pr_issue_id_pretty(resources, id).await?,
-        notify = empty_or_start_new_line(&settings.notify.subscribers_markdown()),
+        "{closed}{notify}",
+        closed = lang.fill(
+            Key::PRIssueClosed,
+            &[("id", &pr_issue_id_pretty(resources, id).await?)]
+        ),
+        notify = empty_or_start_new_line(&settings.notify.subscribers_markdown(lang)),
     ))
 }
 
 pub fn branch_check_message(
+    lang: Language,
     repo: &str,
     branch: &str,
     settings: &BranchSettings,
@@ -136,8 +342,9 @@ pub fn branch_check_message(
     let status = if result.old == result.new {
         format!(
             "{}
-\\(not changed\\)",
-            markdown_optional_commit(result.new.as_deref(), github_info)
+{}",
+            markdown_optional_commit(result.new.as_deref(), github_info),
+            lang.text(Key::BranchNotChanged),
         )
     } else if let (Some(info), Some(old), Some(new)) = (github_info, &result.old, &result.new) {
         github_commit_diff(info, old, new)
@@ -149,13 +356,529 @@ pub fn branch_check_message(
             new = markdown_optional_commit(result.new.as_deref(), github_info),
         )
     };
+    let change = match &result.change {
+        BranchChange::Unchanged | BranchChange::FastForward => "".to_string(),
+        BranchChange::Created => format!("\n{}", lang.text(Key::BranchCreated)),
+        BranchChange::Deleted => format!("\n{}", lang.text(Key::BranchDeleted)),
+        BranchChange::ForcePush {
+            merge_base,
+            dropped,
+            added,
+        } => format!(
+            "\n{}",
+            lang.fill(
+                Key::BranchForcePushed,
+                &[
+                    ("dropped", &dropped.to_string()),
+                    ("added", &added.to_string()),
+                    ("base", &markdown::escape(short_commit(merge_base))),
+                ]
+            )
+        ),
+        BranchChange::Recreate => format!("\n{}", lang.text(Key::BranchRecreated)),
+        BranchChange::UnknownBase => format!("\n{}", lang.text(Key::BranchUnknownBase)),
+    };
+    let stale = match result.stale {
+        Some(BranchStale::Stale(since)) => format!(
+            "\n{}",
+            lang.fill(
+                Key::BranchStale,
+                &[("elapsed", &markdown::escape(&elapsed_pretty(since)))]
+            )
+        ),
+        Some(BranchStale::MovingAgain(since)) => format!(
+            "\n{}",
+            lang.fill(
+                Key::BranchMovingAgain,
+                &[("elapsed", &markdown::escape(&elapsed_pretty(since)))]
+            )
+        ),
+        None => "".to_string(),
+    };
+    let additional = branch_check_message_additional(lang, settings, result);
+    let details = if additional.is_empty() {
+        additional
+    } else {
+        markdown::expandable_blockquote(&additional)
+    };
     format!(
         "{repo}/`{branch}`
-{status}{notify}
-",
+{status}{change}{stale}{notify}
+{details}",
+        repo = markdown::escape(repo),
+        branch = markdown::escape(branch),
+        notify = empty_or_start_new_line(&settings.notify.subscribers_markdown(lang)),
+    )
+}
+
+pub fn branch_template_values(
+    lang: Language,
+    repo: &str,
+    branch: &str,
+    settings: &BranchSettings,
+    result: &BranchCheckResult,
+    github_info: Option<&GitHubInfo>,
+) -> TemplateValues {
+    vec![
+        ("repo", markdown::escape(repo)),
+        ("branch", markdown::escape(branch)),
+        ("comment", settings.notify.description_markdown()),
+        (
+            "old",
+            markdown_optional_commit(result.old.as_deref(), github_info),
+        ),
+        (
+            "new",
+            markdown_optional_commit(result.new.as_deref(), github_info),
+        ),
+        ("change", branch_change_pretty(result)),
+        ("commits", result.commits.len().to_string()),
+        ("subscribers", settings.notify.subscribers_markdown(lang)),
+    ]
+}
+
+pub fn branch_check_summary(repo: &str, branch: &str, result: &BranchCheckResult) -> String {
+    format!("[{repo}] {branch} {}", branch_change_text(result))
+}
+
+fn branch_change_text(result: &BranchCheckResult) -> String {
+    match (&result.change, result.stale) {
+        (BranchChange::Unchanged, Some(BranchStale::Stale(_))) => "stale".to_string(),
+        (BranchChange::Unchanged, _) => "not changed".to_string(),
+        (BranchChange::Created, _) => "created".to_string(),
+        (BranchChange::Deleted, _) => "deleted".to_string(),
+        (BranchChange::FastForward, _) => format!(
+            "updated to {}",
+            short_commit(result.new.as_deref().unwrap_or_default())
+        ),
+        (BranchChange::ForcePush { .. }, _) => "force-pushed".to_string(),
+        (BranchChange::Recreate, _) => "recreated".to_string(),
+        (BranchChange::UnknownBase, _) => "force-pushed (unknown base)".to_string(),
+    }
+}
+
+fn branch_change_pretty(result: &BranchCheckResult) -> String {
+    match (&result.change, result.new.as_deref()) {
+        (BranchChange::FastForward, Some(new)) => {
+            format!("updated to `{}`", markdown::escape(short_commit(new)))
+        }
+        _ => markdown::escape(&branch_change_text(result)),
+    }
+}
+
+const MAX_LISTED_SUPPRESSED: usize = 20;
+
+pub fn mute_summary_message(
+    lang: Language,
+    target: &str,
+    mute: &MuteSettings,
+    expired: bool,
+) -> String {
+    let count = mute.suppressed.len();
+    let state = if expired {
+        Key::MuteExpired
+    } else {
+        Key::Unmuted
+    };
+    let suppressed = if count == 1 {
+        Key::MuteSuppressedNotification
+    } else {
+        Key::MuteSuppressedNotifications
+    };
+    let mut lines = vec![format!(
+        "{}{}",
+        lang.fill(state, &[("target", target)]),
+        lang.fill(suppressed, &[("count", &count.to_string())]),
+    )];
+    if count != 0 {
+        let mut details: Vec<_> = mute
+            .suppressed
+            .iter()
+            .take(MAX_LISTED_SUPPRESSED)
+            .map(|summary| format!("\\- {summary}"))
+            .collect();
+        if count > MAX_LISTED_SUPPRESSED {
+            let more = (count - MAX_LISTED_SUPPRESSED).to_string();
+            details.push(lang.fill(Key::AndMore, &[("count", &more)]));
+        }
+        lines.push(markdown::expandable_blockquote(&details.join("\n")));
+    }
+    lines.join("\n")
+}
+
+const MAX_DIGEST_GROUP_ITEMS: usize = 10;
+
+// split into messages by the caller
+pub fn digest_lines(lang: Language, since: DateTime<Utc>, events: &[DigestEvent]) -> Vec<String> {
+    let mut groups: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+    for event in events {
+        groups
+            .entry(&event.repo)
+            .or_default()
+            .entry(&event.category)
+            .or_default()
+            .push(&event.summary);
+    }
+    let header = if events.len() == 1 {
+        Key::DigestEvent
+    } else {
+        Key::DigestEvents
+    };
+    let mut lines = vec![lang.fill(
+        header,
+        &[
+            ("count", &events.len().to_string()),
+            ("elapsed", &markdown::escape(&elapsed_pretty(since))),
+        ],
+    )];
+    for (repo, categories) in groups {
+        lines.push(format!("\n*{}*", markdown::escape(repo)));
+        for (category, summaries) in categories {
+            lines.push(format!("_{}_:", category_title(lang, category)));
+            for summary in summaries.iter().take(MAX_DIGEST_GROUP_ITEMS) {
+                lines.push(format!("\\- {summary}"));
+            }
+            if summaries.len() > MAX_DIGEST_GROUP_ITEMS {
+                let more = (summaries.len() - MAX_DIGEST_GROUP_ITEMS).to_string();
+                lines.push(lang.fill(Key::AndMore, &[("count", &more)]));
+            }
+        }
+    }
+    lines
+}
+
+// categories of `Item::category`
+fn category_title(lang: Language, category: &str) -> String {
+    let key = match category {
+        "commits" => Key::CategoryCommits,
+        "branches" => Key::CategoryBranches,
+        "PRs/issues" => Key::CategoryPRIssues,
+        "others" => Key::CategoryOthers,
+        _ => return markdown::escape(category),
+    };
+    lang.text(key).to_string()
+}
+
+pub fn dashboard_header(lang: Language) -> String {
+    let updated_at = time_pretty(Utc::now());
+    lang.fill(
+        Key::DashboardTitle,
+        &[("time", &markdown::escape(&updated_at))],
+    )
+}
+
+// without the header, contents should not change with time
+pub fn dashboard_lines(
+    lang: Language,
+    items: &[(String, ChatRepoSettings, ChatRepoResults)],
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (repo, settings, results) in items {
+        lines.push(format!("\n*{}*", markdown::escape(repo)));
+        if !settings.commits.is_empty() {
+            lines.push(format!("_{}_:", lang.text(Key::CategoryCommits)));
+        }
+        for (hash, commit) in &settings.commits {
+            let branches = results
+                .commits
+                .get(hash)
+                .map(|r| markdown_list_compat(r.branches.iter()))
+                .unwrap_or_else(|| lang.text(Key::NotChecked).to_string());
+            lines.push(format!(
+                "\\- `{hash}` {comment} \u{2192} {branches}",
+                hash = markdown::escape(short_commit(hash)),
+                comment = commit.notify.description_markdown(),
+            ));
+        }
+        if !settings.pr_issues.is_empty() {
+            lines.push(format!("_{}_:", lang.text(Key::CategoryPRIssues)));
+        }
+        for (id, pr_issue) in &settings.pr_issues {
+            let state = match pr_issue.closed_at {
+                Some(closed_at) => {
+                    let time = time_pretty(closed_at);
+                    lang.fill(Key::PRIssueClosedAt, &[("time", &markdown::escape(&time))])
+                }
+                None => lang.text(Key::PRIssueOpen).to_string(),
+            };
+            lines.push(format!(
+                "\\- {link} {comment} \u{2192} {state}",
+                link = markdown::link(pr_issue.url.as_str(), &id.to_string()),
+                comment = pr_issue.notify.description_markdown(),
+            ));
+        }
+        if !settings.branches.is_empty() {
+            lines.push(format!("_{}_:", lang.text(Key::CategoryBranches)));
+        }
+        for branch in settings.branches.keys() {
+            let head = results
+                .branches
+                .get(branch)
+                .and_then(|r| r.commit.as_deref());
+            lines.push(format!(
+                "\\- `{branch}` \u{2192} {head}",
+                branch = markdown::escape(branch),
+                head = markdown_optional_commit(head.map(short_commit), None),
+            ));
+        }
+    }
+    lines
+}
+
+// standalone status of tracked items, e.g. for inline query results
+pub fn commit_status_message(
+    lang: Language,
+    repo: &str,
+    hash: &str,
+    settings: &CommitSettings,
+    results: Option<&CommitResults>,
+) -> String {
+    let branches = results
+        .map(|r| markdown_list_compat(r.branches.iter()))
+        .unwrap_or_else(|| lang.text(Key::NotChecked).to_string());
+    let mut result = format!(
+        "\\[{repo}\\] commit `{hash}`\n{comment}\n{branches}",
+        repo = markdown::escape(repo),
+        hash = markdown::escape(short_commit(hash)),
+        comment = settings.notify.description_markdown(),
+        branches = lang.fill(Key::StatusIn, &[("branches", &branches)]),
+    );
+    if let Some(url) = &settings.url {
+        result.push('\n');
+        result.push_str(&markdown::escape(url.as_str()));
+    }
+    result
+}
+
+pub fn pr_issue_status_message(
+    lang: Language,
+    repo: &str,
+    id: u64,
+    settings: &PRIssueSettings,
+) -> String {
+    let state = match settings.closed_at {
+        Some(closed_at) => {
+            let time = time_pretty(closed_at);
+            lang.fill(Key::PRIssueClosedAt, &[("time", &markdown::escape(&time))])
+        }
+        None => lang.text(Key::PRIssueOpen).to_string(),
+    };
+    format!(
+        "\\[{repo}\\] {link}\n{comment}\n{state}",
+        repo = markdown::escape(repo),
+        link = markdown::link(settings.url.as_str(), &format!("\\#{id}")),
+        comment = settings.notify.description_markdown(),
+        state = lang.fill(Key::StatusState, &[("state", &state)]),
+    )
+}
+
+pub fn branch_status_message(
+    lang: Language,
+    repo: &str,
+    branch: &str,
+    settings: &BranchSettings,
+    results: Option<&BranchResults>,
+) -> String {
+    let head = results.and_then(|r| r.commit.as_deref());
+    let mut result = format!(
+        "\\[{repo}\\] branch `{branch}`\n{comment}\n{head}",
         repo = markdown::escape(repo),
         branch = markdown::escape(branch),
-        notify = empty_or_start_new_line(&settings.notify.subscribers_markdown()),
+        comment = settings.notify.description_markdown(),
+        head = lang.fill(
+            Key::StatusHead,
+            &[(
+                "head",
+                &markdown_optional_commit(head.map(short_commit), None)
+            )]
+        ),
+    );
+    if let Some(changed_at) = results.and_then(|r| r.changed_at) {
+        result.push('\n');
+        result.push_str(&lang.fill(
+            Key::StatusChanged,
+            &[("elapsed", &markdown::escape(&elapsed_pretty(changed_at)))],
+        ));
+    }
+    result
+}
+
+// packs lines into as few messages as possible
+// lines longer than `max_length` are replaced by an ellipsis
+pub fn split_lines(lines: Vec<String>, max_length: usize) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        let line = truncate_markdown(line, max_length);
+        let length = current.chars().count() + line.chars().count() + 1;
+        if !current.is_empty() && length > max_length {
+            messages.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+// cutting formatted text may leave an entity unclosed, which fails the whole message
+fn truncate_markdown(line: String, max_length: usize) -> String {
+    if line.chars().count() <= max_length {
+        line
+    } else {
+        "\u{2026}".to_string()
+    }
+}
+
+pub fn elapsed_pretty(since: DateTime<Utc>) -> String {
+    let elapsed = (Utc::now() - since).to_std().unwrap_or_default();
+    // minute precision is enough
+    let rounded = Duration::from_secs(elapsed.as_secs() / 60 * 60);
+    humantime::format_duration(rounded).to_string()
+}
+
+const MAX_LISTED_BRANCH_COMMITS: usize = 10;
+
+pub fn branch_check_message_additional(
+    lang: Language,
+    settings: &BranchSettings,
+    result: &BranchCheckResult,
+) -> String {
+    let mut lines = Vec::new();
+    let count = result.commits.len();
+    let filter = if result.filtered {
+        lang.fill(
+            Key::BranchTouching,
+            &[("paths", &markdown_list_compat(settings.paths.iter()))],
+        )
+    } else {
+        "".to_string()
+    };
+    if result.unlisted_touching {
+        lines.push(lang.fill(
+            Key::BranchUnlistedCommits,
+            &[
+                ("count", &MAX_BRANCH_COMMITS.to_string()),
+                ("filter", &filter),
+            ],
+        ));
+    }
+    if count != 0 {
+        let count_text = if result.truncated {
+            format!("{count}\\+")
+        } else {
+            count.to_string()
+        };
+        let key = if count == 1 && !result.truncated {
+            Key::BranchNewCommit
+        } else {
+            Key::BranchNewCommits
+        };
+        lines.push(format!(
+            "{new}{filter}:",
+            new = lang.fill(key, &[("count", &count_text)]),
+        ));
+        for commit in result.commits.iter().take(MAX_LISTED_BRANCH_COMMITS) {
+            lines.push(format!(
+                "\\- `{id}` {summary} \\({author}\\)",
+                id = markdown::escape(short_commit(&commit.id)),
+                summary = markdown::escape(&commit.summary),
+                author = markdown::escape(&commit.author),
+            ));
+        }
+        if count > MAX_LISTED_BRANCH_COMMITS {
+            let more = (count - MAX_LISTED_BRANCH_COMMITS).to_string();
+            lines.push(lang.fill(Key::AndMore, &[("count", &more)]));
+        }
+    }
+    if let Some(stat) = &result.diff_stat {
+        let key = if stat.files == 1 {
+            Key::BranchDiffStatFile
+        } else {
+            Key::BranchDiffStatFiles
+        };
+        lines.push(lang.fill(key, &[("files", &stat.files.to_string())]));
+    }
+    lines.join("\n")
+}
+
+pub fn branch_compare_message(
+    lang: Language,
+    repo: &str,
+    branch: &str,
+    base: &str,
+    result: &BranchPairResults,
+) -> String {
+    lang.fill(
+        Key::BranchCompare,
+        &[
+            ("repo", &markdown::escape(repo)),
+            ("branch", &markdown::escape(branch)),
+            ("base", &markdown::escape(base)),
+            ("ahead", &result.ahead.to_string()),
+            ("behind", &result.behind.to_string()),
+        ],
+    )
+}
+
+pub fn branch_pair_template_values(
+    lang: Language,
+    repo: &str,
+    settings: &BranchPairSettings,
+    result: &BranchPairCheckResult,
+) -> TemplateValues {
+    vec![
+        ("repo", markdown::escape(repo)),
+        ("branch", markdown::escape(&settings.branch)),
+        ("base", markdown::escape(&settings.base)),
+        ("ahead", result.new.ahead.to_string()),
+        ("behind", result.new.behind.to_string()),
+        ("comment", settings.notify.description_markdown()),
+        ("subscribers", settings.notify.subscribers_markdown(lang)),
+    ]
+}
+
+pub fn branch_pair_check_message(
+    lang: Language,
+    repo: &str,
+    settings: &BranchPairSettings,
+    result: &BranchPairCheckResult,
+) -> String {
+    let event = match result.event {
+        Some(BranchPairEvent::ThresholdCrossed(threshold)) => format!(
+            "\n{}",
+            lang.fill(
+                Key::BranchPairLagging,
+                &[("threshold", &threshold.to_string())]
+            )
+        ),
+        Some(BranchPairEvent::CaughtUp) => format!("\n{}", lang.text(Key::BranchPairCaughtUp)),
+        None => "".to_string(),
+    };
+    format!(
+        "{compare}{event}{notify}",
+        compare = branch_compare_message(lang, repo, &settings.branch, &settings.base, &result.new),
+        notify = empty_or_start_new_line(&settings.notify.subscribers_markdown(lang)),
+    )
+}
+
+pub fn branch_pair_check_summary(
+    repo: &str,
+    settings: &BranchPairSettings,
+    result: &BranchPairCheckResult,
+) -> String {
+    let event = match result.event {
+        Some(BranchPairEvent::ThresholdCrossed(_)) => format!("{} behind", result.new.behind),
+        Some(BranchPairEvent::CaughtUp) | None => "caught up".to_string(),
+    };
+    format!(
+        "[{repo}] {branch} vs {base}: {event}",
+        branch = settings.branch,
+        base = settings.base,
     )
 }
 
@@ -226,3 +949,105 @@ where
 pub fn subscriber_from_msg(msg: &Message) -> Option<Subscriber> {
     msg.from.as_ref().map(Subscriber::from_tg_user)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::settings::NotifySettings;
+
+    #[test]
+    fn split_long_lines() {
+        let lines = vec!["a".repeat(6), "b".repeat(3), "c".repeat(3)];
+        assert_eq!(
+            split_lines(lines, 8),
+            vec!["aaaaaa".to_string(), "bbb\nccc".to_string()]
+        );
+        let lines = vec!["a".repeat(10), "b".repeat(3)];
+        assert_eq!(split_lines(lines, 5), vec!["\u{2026}\nbbb".to_string()]);
+        // entities are never cut open
+        let lines = vec!["`abc\\.def`".to_string(), "[x](https://x.org)".to_string()];
+        assert_eq!(
+            split_lines(lines, 8),
+            vec!["\u{2026}\n\u{2026}".to_string()]
+        );
+    }
+
+    fn notify(comment: &str) -> NotifySettings {
+        NotifySettings {
+            comment: comment.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn commit_status() {
+        let hash = "0123456789abcdef0123456789abcdef01234567";
+        let mut settings = CommitSettings {
+            url: None,
+            living: false,
+            notify: notify("Fix the build.\ndetails"),
+        };
+        assert_eq!(
+            commit_status_message(Language::En, "nix-pkgs", hash, &settings, None),
+            format!(
+                "\\[nix\\-pkgs\\] commit `{}`\nFix the build\\.\n*in*: \\(not checked\\)",
+                short_commit(hash)
+            )
+        );
+        settings.url = Some("https://example.com/1".parse().unwrap());
+        let results = CommitResults {
+            branches: ["master".to_string(), "staging".to_string()].into(),
+            message: None,
+        };
+        let message =
+            commit_status_message(Language::En, "nix-pkgs", hash, &settings, Some(&results));
+        assert!(message.contains("*in*: `master` `staging`\n"));
+        assert!(message.ends_with("https://example\\.com/1"));
+    }
+
+    #[test]
+    fn pr_issue_status() {
+        let mut settings = PRIssueSettings {
+            url: "https://github.com/NixOS/nixpkgs/pull/42".parse().unwrap(),
+            closed_at: None,
+            notify: notify("update foo"),
+        };
+        assert_eq!(
+            pr_issue_status_message(Language::En, "nixpkgs", 42, &settings),
+            "\\[nixpkgs\\] [\\#42](https://github.com/NixOS/nixpkgs/pull/42)\nupdate foo\n*state*: open"
+        );
+        settings.closed_at = DateTime::from_timestamp(1_714_564_800, 0);
+        assert!(
+            pr_issue_status_message(Language::En, "nixpkgs", 42, &settings)
+                .ends_with("*state*: closed at 2024\\-05\\-01 12:00 UTC")
+        );
+        assert!(
+            pr_issue_status_message(Language::ZhCn, "nixpkgs", 42, &settings)
+                .ends_with("*状态*：已于 2024\\-05\\-01 12:00 UTC 关闭")
+        );
+    }
+
+    #[test]
+    fn branch_status() {
+        let settings = BranchSettings {
+            notify: notify(""),
+            ..Default::default()
+        };
+        assert_eq!(
+            branch_status_message(Language::En, "nixpkgs", "release-24.05", &settings, None),
+            "\\[nixpkgs\\] branch `release\\-24\\.05`\n\n*head*: \\(nothing\\)"
+        );
+        let hash = "0123456789abcdef0123456789abcdef01234567";
+        let results = BranchResults {
+            commit: Some(hash.to_string()),
+            changed_at: Some(Utc::now() - chrono::Duration::minutes(5)),
+            ..Default::default()
+        };
+        let message =
+            branch_status_message(Language::En, "nixpkgs", "master", &settings, Some(&results));
+        assert!(message.contains(&format!("*head*: `{}`", short_commit(hash))));
+        assert!(message.ends_with("*changed*: 5m ago"));
+        let message = branch_status_message(Language::ZhCn, "nixpkgs", "master", &settings, None);
+        assert!(message.ends_with("*最新提交*：\\(nothing\\)"));
+    }
+}
@@ -16,7 +16,10 @@ pub async fn from_0_2_1() -> Result<(), Error> {
         mem::swap(&mut subscribers, &mut settings.subscribers);
         for subscriber in subscribers {
             match subscriber {
-                Subscriber::Telegram { markdown_mention } => {
+                Subscriber::Telegram {
+                    markdown_mention,
+                    user_id,
+                } => {
                     if markdown_mention.starts_with("@") {
                         let new_mention = markdown::escape(&markdown_mention);
                         if new_mention != markdown_mention {
@@ -24,11 +27,13 @@ pub async fn from_0_2_1() -> Result<(), Error> {
                         }
                         settings.subscribers.insert(Subscriber::Telegram {
                             markdown_mention: new_mention,
+                            user_id,
                         });
                     } else {
-                        settings
-                            .subscribers
-                            .insert(Subscriber::Telegram { markdown_mention });
+                        settings.subscribers.insert(Subscriber::Telegram {
+                            markdown_mention,
+                            user_id,
+                        });
                     }
                 }
             }
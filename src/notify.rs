@@ -0,0 +1,246 @@
+use chrono::{DateTime, Utc};
+use teloxide::{
+    ApiError, Bot, RequestError,
+    payloads::{EditMessageTextSetters, SendMessageSetters},
+    prelude::Requester,
+    sugar::request::RequestLinkPreviewExt,
+    types::{ChatId, InlineKeyboardMarkup, MessageId, ParseMode, ReplyParameters},
+};
+
+use crate::{
+    CommandError,
+    chat::{
+        self, Item,
+        resources::ChatRepoResources,
+        results::{DeferredNotification, DigestEvent, HistoryKind, Notification},
+        settings::{Delivery, NotifySettings, QuietMode, Subscriber},
+    },
+    error::Error,
+    message::{digest_lines, split_lines},
+    subscribe_button_markup,
+};
+
+// leave some room below the telegram limit of 4096
+const MAX_DIGEST_MESSAGE_LENGTH: usize = 4000;
+
+// takes subscribers who prefer private messages, those who should not be mentioned in the chat are
+// removed from `notify`
+pub async fn private_recipients(
+    chat: ChatId,
+    notify: &mut NotifySettings,
+) -> Result<Vec<(Subscriber, Delivery)>, Error> {
+    let chat_resources = chat::chat_resources(chat).await?;
+    let settings = chat_resources.settings.read().await;
+    let mut recipients = Vec::new();
+    for subscriber in &notify.subscribers {
+        let delivery = subscriber
+            .user_id()
+            .and_then(|id| settings.delivery.get(&id))
+            .copied()
+            .unwrap_or_default();
+        if delivery != Delivery::Mention {
+            recipients.push((subscriber.clone(), delivery));
+        }
+    }
+    notify
+        .subscribers
+        .retain(|s| !recipients.contains(&(s.clone(), Delivery::Private)));
+    Ok(recipients)
+}
+
+pub async fn send(
+    bot: &Bot,
+    resources: &ChatRepoResources,
+    notification: Notification,
+) -> Result<(), CommandError> {
+    let chat = resources.task.chat;
+    let repo = &resources.task.repo;
+    if chat::mute_record(resources, notification.item.as_ref(), &notification.summary).await? {
+        log::info!(
+            "notification muted ({chat}, {repo}): {}",
+            notification.summary
+        );
+        return Ok(());
+    }
+    let event = DigestEvent {
+        repo: repo.clone(),
+        category: notification
+            .item
+            .as_ref()
+            .map(Item::category)
+            .unwrap_or("others")
+            .to_string(),
+        summary: notification.summary.clone(),
+    };
+    let chat_resources = chat::chat_resources(chat).await?;
+    if chat::digest_queue(&chat_resources, notification.urgent, event).await? {
+        log::info!(
+            "notification queued for digest ({chat}, {repo}): {}",
+            notification.summary
+        );
+        return Ok(());
+    }
+    let quiet = chat::quiet_mode(&chat_resources).await;
+    if quiet == Some(QuietMode::Defer) {
+        log::info!(
+            "notification deferred until quiet hours end ({chat}, {repo}): {}",
+            notification.summary
+        );
+        let deferred = DeferredNotification {
+            repo: repo.clone(),
+            notification,
+        };
+        chat::defer(&chat_resources, deferred).await?;
+        return Ok(());
+    }
+    deliver(bot, chat, repo, notification, quiet.is_some()).await
+}
+
+// sends without checking mute, digest or quiet hours
+pub async fn deliver(
+    bot: &Bot,
+    chat: ChatId,
+    repo: &str,
+    notification: Notification,
+    silent: bool,
+) -> Result<(), CommandError> {
+    let markup = match &notification.item {
+        Some(item) if notification.subscribe_button => {
+            let (kind, id) = match item {
+                Item::Commit(hash) => ("c", hash.clone()),
+                Item::Branch(branch) => ("b", branch.clone()),
+                Item::PRIssue(id) => ("p", id.to_string()),
+            };
+            match subscribe_button_markup(kind, repo, &id) {
+                Ok(m) => Some(m),
+                Err(e) => {
+                    log::error!("failed to create markup for ({chat}, {repo}, {id}): {e}");
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    let living = match &notification.item {
+        Some(item) if notification.living => {
+            let resources = chat::resources_chat_repo(chat, repo.to_string()).await?;
+            let previous = chat::living_message(&resources, item).await;
+            Some((resources, item, previous))
+        }
+        _ => None,
+    };
+    let edited = match &living {
+        Some((_, _, Some(previous))) => {
+            edit_living(bot, chat, *previous, &notification, markup.clone()).await
+        }
+        _ => None,
+    };
+    let sent = match edited {
+        Some(id) => {
+            // edits do not notify anyone
+            if !notification.mentions.is_empty() {
+                bot.send_message(chat, notification.mentions.clone())
+                    .parse_mode(ParseMode::MarkdownV2)
+                    .disable_notification(silent)
+                    .reply_parameters(ReplyParameters::new(id))
+                    .await?;
+            }
+            id
+        }
+        None => {
+            let mut send = bot
+                .send_message(chat, notification.message.clone())
+                .parse_mode(ParseMode::MarkdownV2)
+                .disable_notification(silent);
+            if !notification.link_preview {
+                send = send.disable_link_preview(true);
+            }
+            if let Some(markup) = markup {
+                send = send.reply_markup(markup);
+            }
+            let id = send.await?.id;
+            if let Some((resources, item, _)) = &living {
+                chat::living_message_set(resources, item, id).await?;
+            }
+            id
+        }
+    };
+    // private messages are still sent if the history can not be saved
+    let summary = notification.summary.clone();
+    chat::history_record_chat(chat, Some(repo), HistoryKind::Sent, summary).await;
+
+    let mut fallback = Vec::new();
+    for (subscriber, delivery) in notification.private {
+        let Some(user_id) = subscriber.user_id() else {
+            continue;
+        };
+        let result = bot
+            .send_message(user_id, notification.message.clone())
+            .parse_mode(ParseMode::MarkdownV2)
+            .disable_link_preview(true)
+            .disable_notification(silent)
+            .await;
+        if let Err(e) = result {
+            // usually the user has never started a conversation with the bot
+            log::warn!("failed to send private message to {user_id} ({chat}, {repo}): {e}");
+            if delivery == Delivery::Private {
+                fallback.push(subscriber.markdown().to_string());
+            }
+        }
+    }
+    if !fallback.is_empty() {
+        bot.send_message(chat, fallback.join(" "))
+            .parse_mode(ParseMode::MarkdownV2)
+            .disable_notification(silent)
+            .reply_parameters(ReplyParameters::new(sent))
+            .await?;
+    }
+    Ok(())
+}
+
+// returns `None` if the message can not be edited, e.g. deleted by someone
+async fn edit_living(
+    bot: &Bot,
+    chat: ChatId,
+    id: MessageId,
+    notification: &Notification,
+    markup: Option<InlineKeyboardMarkup>,
+) -> Option<MessageId> {
+    let mut edit = bot
+        .edit_message_text(chat, id, notification.message.clone())
+        .parse_mode(ParseMode::MarkdownV2);
+    if !notification.link_preview {
+        edit = edit.disable_link_preview(true);
+    }
+    if let Some(markup) = markup {
+        edit = edit.reply_markup(markup);
+    }
+    match edit.await {
+        Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => Some(id),
+        Err(e) => {
+            log::warn!("failed to edit living message {id} in {chat}, sending a new one: {e}");
+            None
+        }
+    }
+}
+
+// returns the first line for history
+pub async fn send_digest(
+    bot: &Bot,
+    chat: ChatId,
+    since: DateTime<Utc>,
+    events: &[DigestEvent],
+    silent: bool,
+) -> Result<String, CommandError> {
+    let lang = chat::language(chat).await;
+    let lines = digest_lines(lang, since, events);
+    let summary = lines.first().cloned().unwrap_or_default();
+    for text in split_lines(lines, MAX_DIGEST_MESSAGE_LENGTH) {
+        bot.send_message(chat, text)
+            .parse_mode(ParseMode::MarkdownV2)
+            .disable_link_preview(true)
+            .disable_notification(silent)
+            .await?;
+    }
+    Ok(summary)
+}
@@ -13,6 +13,13 @@ pub struct Options {
     pub admin_chat_id: i64,
     #[arg(short, long, default_value = "1week")]
     pub pr_issue_expire: Duration,
+    // default per-chat limits, admins may override them per chat
+    #[arg(long, default_value_t = 200)]
+    pub max_commits: usize,
+    #[arg(long, default_value_t = 200)]
+    pub max_pr_issues: usize,
+    #[arg(long, default_value_t = 50)]
+    pub max_branches: usize,
 }
 
 pub static OPTIONS: once_cell::sync::OnceCell<Options> = once_cell::sync::OnceCell::new();
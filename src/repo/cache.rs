@@ -8,10 +8,19 @@ pub fn initialize(cache: &Connection) -> Result<(), Error> {
     cache.execute(
         "CREATE TABLE IF NOT EXISTS commits_cache (
             branch        TEXT    NOT NULL,
-            commit_hash   TEXT    NOT NULL
+            commit_hash   TEXT    NOT NULL,
+            seen_at       INTEGER
         )",
         [],
     )?;
+    // caches created before first-seen times were recorded
+    if cache
+        .prepare("SELECT seen_at FROM commits_cache LIMIT 0")
+        .is_err()
+    {
+        log::info!("adding seen_at column to commits cache");
+        cache.execute("ALTER TABLE commits_cache ADD COLUMN seen_at INTEGER", [])?;
+    }
     cache.execute(
         "CREATE TABLE IF NOT EXISTS branches (
             branch           TEXT    NOT NULL PRIMARY KEY,
@@ -24,6 +33,11 @@ pub fn initialize(cache: &Connection) -> Result<(), Error> {
          ON commits_cache (commit_hash)",
         [],
     )?;
+    cache.execute(
+        "CREATE INDEX IF NOT EXISTS idx_branch_seen_at
+         ON commits_cache (branch, seen_at)",
+        [],
+    )?;
 
     Ok(())
 }
@@ -97,22 +111,34 @@ pub fn query_cache_commit(cache: &Connection, commit: &str) -> Result<BTreeSet<S
         .collect::<Result<_, _>>()?)
 }
 
-pub fn store_cache(cache: &Connection, branch: &str, commit: &str) -> Result<(), Error> {
-    let mut stmt =
-        cache.prepare_cached("INSERT INTO commits_cache (branch, commit_hash) VALUES (?1, ?2)")?;
-    log::trace!("insert new cache: ({branch}, {commit})");
-    let inserted = stmt.execute(params!(branch, commit))?;
+// `seen_at` is the unix timestamp the commit is first seen in the branch, `None` if unknown
+pub fn store_cache(
+    cache: &Connection,
+    branch: &str,
+    commit: &str,
+    seen_at: Option<i64>,
+) -> Result<(), Error> {
+    let mut stmt = cache.prepare_cached(
+        "INSERT INTO commits_cache (branch, commit_hash, seen_at) VALUES (?1, ?2, ?3)",
+    )?;
+    log::trace!("insert new cache: ({branch}, {commit}, {seen_at:?})");
+    let inserted = stmt.execute(params!(branch, commit, seen_at))?;
     assert_eq!(inserted, 1);
     Ok(())
 }
 
-pub fn batch_store_cache<I>(cache: &Connection, branch: &str, commits: I) -> Result<(), Error>
+pub fn batch_store_cache<I>(
+    cache: &Connection,
+    branch: &str,
+    commits: I,
+    seen_at: Option<i64>,
+) -> Result<(), Error>
 where
     I: IntoIterator<Item = String>,
 {
     let mut count = 0usize;
     for c in commits.into_iter() {
-        store_cache(cache, branch, &c)?;
+        store_cache(cache, branch, &c, seen_at)?;
         count += 1;
         if count.is_multiple_of(100000) {
             log::debug!("batch storing cache, current count: {count}",);
@@ -120,3 +146,68 @@ where
     }
     Ok(())
 }
+
+pub fn query_seen_at(cache: &Connection, branch: &str, commit: &str) -> Result<Option<i64>, Error> {
+    let mut stmt = cache.prepare_cached(
+        "SELECT seen_at FROM commits_cache WHERE branch = ?1 AND commit_hash = ?2",
+    )?;
+    let mut query_result = stmt.query(params!(branch, commit))?;
+    match query_result.next()? {
+        Some(row) => Ok(row.get(0)?),
+        None => Ok(None),
+    }
+}
+
+// seconds for recent commits to propagate from one branch to another, newest first
+pub fn query_latencies(
+    cache: &Connection,
+    from: &str,
+    to: &str,
+    limit: u32,
+) -> Result<Vec<i64>, Error> {
+    let mut stmt = cache.prepare_cached(
+        "SELECT t.seen_at - f.seen_at FROM commits_cache t
+         JOIN commits_cache f ON f.commit_hash = t.commit_hash
+         WHERE t.branch = ?2 AND f.branch = ?1
+           AND t.seen_at IS NOT NULL AND f.seen_at IS NOT NULL AND t.seen_at >= f.seen_at
+         ORDER BY t.seen_at DESC
+         LIMIT ?3",
+    )?;
+    log::trace!("query latencies: ({from}, {to})");
+    Ok(stmt
+        .query_map(params!(from, to, limit), |row| row.get(0))?
+        .collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latencies() {
+        let cache = Connection::open_in_memory().unwrap();
+        initialize(&cache).unwrap();
+        store_cache(&cache, "staging", "a", Some(100)).unwrap();
+        store_cache(&cache, "master", "a", Some(160)).unwrap();
+        store_cache(&cache, "staging", "b", Some(200)).unwrap();
+        store_cache(&cache, "master", "b", Some(500)).unwrap();
+        // unknown first-seen times and commits only in one branch are skipped
+        store_cache(&cache, "staging", "c", None).unwrap();
+        store_cache(&cache, "master", "c", Some(600)).unwrap();
+        store_cache(&cache, "staging", "d", Some(700)).unwrap();
+
+        assert_eq!(
+            query_latencies(&cache, "staging", "master", 10).unwrap(),
+            vec![300, 60]
+        );
+        assert_eq!(
+            query_latencies(&cache, "staging", "master", 1).unwrap(),
+            vec![300]
+        );
+        assert!(
+            query_latencies(&cache, "master", "staging", 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
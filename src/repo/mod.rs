@@ -4,8 +4,10 @@ use std::{
     sync::{Arc, LazyLock},
 };
 
-use git2::{Commit, Oid, Repository};
+use chrono::{DateTime, Utc};
+use git2::{Commit, Diff, DiffOptions, ErrorCode, Oid, Repository, Sort};
 use regex::Regex;
+use rusqlite::Connection;
 use tokio::{
     fs::{create_dir_all, read_dir, remove_dir_all},
     sync::Mutex,
@@ -19,7 +21,7 @@ use crate::{
     repo::{
         cache::batch_store_cache,
         paths::RepoPaths,
-        resources::{RESOURCES_MAP, RepoResources},
+        resources::{RESOURCES_MAP, RepoResources, StageLatencies},
         settings::ConditionSettings,
     },
 };
@@ -48,8 +50,8 @@ pub async fn create(name: &str, url: &str) -> Result<Output, Error> {
                 .arg("clone")
                 .arg(url)
                 .arg(path)
-                // blobless clone
-                .arg("--filter=tree:0")
+                // blobless clone, trees are required for path filters
+                .arg("--filter=blob:none")
                 .output()
         })
         .await
@@ -93,10 +95,11 @@ pub async fn list() -> Result<BTreeSet<String>, Error> {
     Ok(result)
 }
 
-pub async fn fetch_and_update_cache(resources: Arc<RepoResources>) -> Result<(), Error> {
+pub async fn fetch_and_update_cache(
+    resources: Arc<RepoResources>,
+) -> Result<BTreeSet<String>, Error> {
     fetch(&resources).await?;
-    update_cache(resources).await?;
-    Ok(())
+    update_cache(resources).await
 }
 
 pub async fn fetch(resources: &RepoResources) -> Result<Output, Error> {
@@ -125,7 +128,7 @@ pub async fn fetch(resources: &RepoResources) -> Result<Output, Error> {
     Ok(output)
 }
 
-pub async fn update_cache(resources: Arc<RepoResources>) -> Result<(), Error> {
+pub async fn update_cache(resources: Arc<RepoResources>) -> Result<BTreeSet<String>, Error> {
     // get the lock before update
     let _guard = resources.cache_update_lock.lock().await;
     let repo = &resources.name;
@@ -143,6 +146,7 @@ pub async fn update_cache(resources: Arc<RepoResources>) -> Result<(), Error> {
     let update_branches = branches.intersection(&old_branches);
     let mut remove_branches: BTreeSet<String> =
         old_branches.difference(&branches).cloned().collect();
+    let mut repo_new_commits = BTreeSet::new();
     for b in update_branches {
         let repo_guard = resources.repo.lock().await;
         let commit: Commit<'_> = branch_commit(&repo_guard, b)?;
@@ -191,16 +195,25 @@ pub async fn update_cache(resources: Arc<RepoResources>) -> Result<(), Error> {
             {
                 let commit_str = commit.id().to_string();
                 let b = b.clone();
-                cache
-                    .interact(move |conn| -> Result<(), Error> {
+                let seen_at = Utc::now().timestamp();
+                let fresh = cache
+                    .interact(move |conn| -> Result<BTreeSet<String>, Error> {
                         let tx = conn.unchecked_transaction()?;
-                        batch_store_cache(&tx, &b, new_commits)?;
+                        // commits not contained by any other branch are new to the repository
+                        let mut fresh = BTreeSet::new();
+                        for c in &new_commits {
+                            if cache::query_cache_commit(&tx, c)?.is_empty() {
+                                fresh.insert(c.clone());
+                            }
+                        }
+                        batch_store_cache(&tx, &b, new_commits, Some(seen_at))?;
                         cache::update_branch(conn, &b, &commit_str)?;
                         tx.commit()?;
-                        Ok(())
+                        Ok(fresh)
                     })
                     .await
                     .map_err(|e| Error::DBInteract(Mutex::new(e)))??;
+                repo_new_commits.extend(fresh);
             }
         } else {
             remove_branches.insert(b.to_owned());
@@ -231,7 +244,8 @@ pub async fn update_cache(resources: Arc<RepoResources>) -> Result<(), Error> {
             cache
                 .interact(move |conn| -> Result<(), Error> {
                     let tx = conn.unchecked_transaction()?;
-                    batch_store_cache(conn, &b, commits)?;
+                    // the first-seen times of existing history are unknown
+                    batch_store_cache(conn, &b, commits, None)?;
                     cache::store_branch(conn, &b, &commit_str)?;
                     tx.commit()?;
                     Ok(())
@@ -240,16 +254,144 @@ pub async fn update_cache(resources: Arc<RepoResources>) -> Result<(), Error> {
                 .map_err(|e| Error::DBInteract(Mutex::new(e)))??;
         }
     }
-    Ok(())
+    log::info!(
+        "find {} commits new to repository {repo}",
+        repo_new_commits.len()
+    );
+    resources.latencies.lock().await.clear();
+    Ok(repo_new_commits)
+}
+
+// number of recent commits used to estimate the latency between two stages
+const LATENCY_SAMPLES: u32 = 100;
+
+// estimated arrival times of the commit at the stages after the `reached` ones, based on the
+// median latency of recent commits between consecutive stages
+pub async fn pipeline_etas(
+    resources: &RepoResources,
+    stages: &[String],
+    reached: usize,
+    commit: &str,
+) -> Result<Vec<(String, DateTime<Utc>)>, Error> {
+    if reached == 0 || reached >= stages.len() {
+        return Ok(Vec::new());
+    }
+    let stages = stages[reached - 1..].to_vec();
+    let commit = commit.to_string();
+    // latencies are shared by all commits checked in an update
+    let mut latencies = resources.latencies.lock().await.clone();
+    let cache = resources.cache().await?;
+    let (etas, latencies) = cache
+        .interact(move |conn| -> Result<_, Error> {
+            let etas = stage_etas(conn, &stages, &commit, &mut latencies)?;
+            Ok((etas, latencies))
+        })
+        .await
+        .map_err(|e| Error::DBInteract(Mutex::new(e)))??;
+    resources.latencies.lock().await.extend(latencies);
+    Ok(etas)
+}
+
+fn stage_etas(
+    conn: &Connection,
+    stages: &[String],
+    commit: &str,
+    latencies: &mut StageLatencies,
+) -> Result<Vec<(String, DateTime<Utc>)>, Error> {
+    let mut etas = Vec::new();
+    let Some(mut eta) = cache::query_seen_at(conn, &stages[0], commit)? else {
+        return Ok(etas);
+    };
+    for pair in stages.windows(2) {
+        let key = (pair[0].clone(), pair[1].clone());
+        let median = match latencies.get(&key) {
+            Some(median) => *median,
+            None => {
+                let mut samples =
+                    cache::query_latencies(conn, &pair[0], &pair[1], LATENCY_SAMPLES)?;
+                samples.sort_unstable();
+                let median = samples.get(samples.len() / 2).copied();
+                latencies.insert(key, median);
+                median
+            }
+        };
+        let Some(median) = median else {
+            break;
+        };
+        eta += median;
+        let Some(time) = DateTime::from_timestamp(eta, 0) else {
+            break;
+        };
+        etas.push((pair[1].clone(), time));
+    }
+    Ok(etas)
 }
 
-fn branch_commit<'repo>(repo: &'repo Repository, branch: &str) -> Result<Commit<'repo>, Error> {
+pub fn branch_commit<'repo>(repo: &'repo Repository, branch: &str) -> Result<Commit<'repo>, Error> {
     let full_name = format!("origin/{branch}");
     let branch = repo.find_branch(&full_name, git2::BranchType::Remote)?;
     let commit = branch.into_reference().peel_to_commit()?;
     Ok(commit)
 }
 
+const MAX_AMBIGUOUS_CANDIDATES: usize = 10;
+const MAX_AMBIGUOUS_SCAN: usize = 100_000;
+
+// resolves abbreviated hashes, tags and revspecs like `master~2` to a full commit hash
+pub async fn resolve_commit(resources: Arc<RepoResources>, spec: &str) -> Result<String, Error> {
+    let spec = spec.to_string();
+    task::spawn_blocking(move || {
+        let repo = resources.repo.blocking_lock();
+        resolve_commit_in(&repo, &spec)
+    })
+    .await?
+}
+
+fn resolve_commit_in(repo: &Repository, spec: &str) -> Result<String, Error> {
+    // branches only exist as remote-tracking references
+    let object = match repo.revparse_single(spec) {
+        Err(e) if e.code() == ErrorCode::NotFound => repo
+            .revparse_single(&format!("origin/{spec}"))
+            .map_err(|_| e),
+        result => result,
+    };
+    // the raw git2 errors are not meant for users
+    match object {
+        Ok(object) => match object.peel_to_commit() {
+            Ok(commit) => Ok(commit.id().to_string()),
+            Err(_) => Err(Error::UnknownCommit(spec.to_string())),
+        },
+        Err(e) if e.code() == ErrorCode::Ambiguous => Err(Error::AmbiguousRevision {
+            spec: spec.to_string(),
+            candidates: ambiguous_candidates(repo, spec)?,
+        }),
+        Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::InvalidSpec) => {
+            Err(Error::UnknownCommit(spec.to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// scanning the whole object database is too slow for large repositories, only commits reachable
+// from fetched branches and tags are listed
+fn ambiguous_candidates(repo: &Repository, prefix: &str) -> Result<Vec<String>, Error> {
+    let prefix = prefix.to_lowercase();
+    let mut walk = repo.revwalk()?;
+    walk.push_glob("refs/remotes/*")?;
+    walk.push_glob("refs/tags/*")?;
+    let mut candidates = Vec::new();
+    for id in walk.take(MAX_AMBIGUOUS_SCAN) {
+        let hex = id?.to_string();
+        if hex.starts_with(&prefix) {
+            candidates.push(hex);
+            if candidates.len() >= MAX_AMBIGUOUS_CANDIDATES {
+                break;
+            }
+        }
+    }
+    Ok(candidates)
+}
+
 pub async fn spawn_gather_commits(
     resources: Arc<RepoResources>,
     commit_id: Oid,
@@ -308,6 +450,74 @@ fn is_parent<'repo>(parent: Commit<'repo>, child: Commit<'repo>) -> bool {
     false
 }
 
+// (commits, truncated), the walk stops at the limit, e.g. when a long-lived branch is merged
+pub fn commits_between(
+    repo: &Repository,
+    old: Oid,
+    new: Oid,
+    limit: usize,
+) -> Result<(Vec<Oid>, bool), Error> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL)?;
+    walk.push(new)?;
+    walk.hide(old)?;
+    let mut commits = Vec::new();
+    for id in walk {
+        if commits.len() == limit {
+            return Ok((commits, true));
+        }
+        commits.push(id?);
+    }
+    Ok((commits, false))
+}
+
+// repositories cloned with `--filter=tree:0` have no trees for path filters and diffs
+pub fn is_missing_object(error: &Error) -> bool {
+    matches!(error, Error::Git(e) if e.code() == ErrorCode::NotFound)
+}
+
+fn diff_options(paths: &[String]) -> DiffOptions {
+    let mut options = DiffOptions::new();
+    for path in paths {
+        options.pathspec(path.as_str());
+    }
+    options
+}
+
+pub fn commit_touches_paths(
+    repo: &Repository,
+    commit: &Commit<'_>,
+    paths: &[String],
+) -> Result<bool, Error> {
+    let tree = commit.tree()?;
+    // compare with the first parent, root commits are compared with the empty tree
+    let parent_tree = match commit.parents().next() {
+        Some(parent) => Some(parent.tree()?),
+        None => None,
+    };
+    let diff = repo.diff_tree_to_tree(
+        parent_tree.as_ref(),
+        Some(&tree),
+        Some(&mut diff_options(paths)),
+    )?;
+    Ok(diff.deltas().len() != 0)
+}
+
+pub fn diff_commits<'repo>(
+    repo: &'repo Repository,
+    old: Oid,
+    new: Oid,
+    paths: &[String],
+) -> Result<Diff<'repo>, Error> {
+    let old_tree = repo.find_commit(old)?.tree()?;
+    let new_tree = repo.find_commit(new)?.tree()?;
+    Ok(repo.diff_tree_to_tree(
+        Some(&old_tree),
+        Some(&new_tree),
+        Some(&mut diff_options(paths)),
+    )?)
+}
+
 pub async fn watching_branches(
     resources: &RepoResources,
     repo: &Repository,
@@ -381,3 +591,145 @@ pub async fn pr_issue_url(resources: &RepoResources, id: u64) -> Result<Url, Err
         None => Err(Error::NoGitHubInfo(resources.name.clone())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use git2::{Signature, Time};
+
+    use super::*;
+
+    // a bare repository with a linear history on `origin/master`, hashes are deterministic
+    fn test_repo(name: &str, commits: usize) -> (Repository, Vec<Oid>) {
+        let path = std::env::temp_dir().join(format!(
+            "commit-notifier-test-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        let repo = Repository::init_bare(&path).unwrap();
+        let mut ids = Vec::new();
+        {
+            let tree_id = repo.treebuilder(None).unwrap().write().unwrap();
+            let tree = repo.find_tree(tree_id).unwrap();
+            let mut parent: Option<Commit> = None;
+            for i in 0..commits {
+                let time = Time::new(i as i64, 0);
+                let signature = Signature::new("test", "test@example.com", &time).unwrap();
+                let parents: Vec<_> = parent.iter().collect();
+                let message = format!("commit {i}");
+                let id = repo
+                    .commit(None, &signature, &signature, &message, &tree, &parents)
+                    .unwrap();
+                parent = Some(repo.find_commit(id).unwrap());
+                ids.push(id);
+            }
+        }
+        repo.reference(
+            "refs/remotes/origin/master",
+            *ids.last().unwrap(),
+            true,
+            "test",
+        )
+        .unwrap();
+        (repo, ids)
+    }
+
+    #[test]
+    fn pipeline_stage_etas() {
+        let conn = Connection::open_in_memory().unwrap();
+        cache::initialize(&conn).unwrap();
+        let stages: Vec<String> = ["staging", "staging-next", "master"]
+            .map(String::from)
+            .to_vec();
+        for (commit, seen_at) in [("a", 0), ("b", 100), ("c", 200)] {
+            cache::store_cache(&conn, "staging", commit, Some(seen_at)).unwrap();
+            cache::store_cache(&conn, "staging-next", commit, Some(seen_at + 10)).unwrap();
+        }
+        cache::store_cache(&conn, "master", "a", Some(1000)).unwrap();
+        cache::store_cache(&conn, "staging", "new", Some(5000)).unwrap();
+
+        let mut latencies = StageLatencies::new();
+        let etas = stage_etas(&conn, &stages, "new", &mut latencies).unwrap();
+        let time = |t| DateTime::from_timestamp(t, 0).unwrap();
+        assert_eq!(
+            etas,
+            vec![
+                ("staging-next".to_string(), time(5010)),
+                ("master".to_string(), time(5000 + 10 + 990)),
+            ]
+        );
+
+        // cached latencies are used without querying again
+        let key = ("staging".to_string(), "staging-next".to_string());
+        assert_eq!(latencies.get(&key), Some(&Some(10)));
+        latencies.insert(key, Some(20));
+        latencies.insert(("staging-next".to_string(), "master".to_string()), None);
+        let etas = stage_etas(&conn, &stages, "new", &mut latencies).unwrap();
+        assert_eq!(etas, vec![("staging-next".to_string(), time(5020))]);
+
+        // not seen in the reached stage yet
+        assert!(
+            stage_etas(&conn, &stages, "unknown", &mut latencies)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn resolve_commits() {
+        let (repo, ids) = test_repo("resolve", 3);
+        let head = ids[2].to_string();
+        assert_eq!(resolve_commit_in(&repo, "master").unwrap(), head);
+        assert_eq!(
+            resolve_commit_in(&repo, "master~2").unwrap(),
+            ids[0].to_string()
+        );
+        assert_eq!(resolve_commit_in(&repo, &head[..10]).unwrap(), head);
+        assert_eq!(
+            resolve_commit_in(&repo, &head.to_uppercase()[..10]).unwrap(),
+            head
+        );
+        assert!(matches!(
+            resolve_commit_in(&repo, "unknown"),
+            Err(Error::UnknownCommit(spec)) if spec == "unknown"
+        ));
+        assert!(matches!(
+            resolve_commit_in(&repo, "master~5"),
+            Err(Error::UnknownCommit(_))
+        ));
+        // trees are not commits
+        assert!(matches!(
+            resolve_commit_in(&repo, "master^{tree}"),
+            Err(Error::UnknownCommit(_))
+        ));
+        let _ = std::fs::remove_dir_all(repo.path());
+    }
+
+    #[test]
+    fn resolve_ambiguous_commits() {
+        let (repo, ids) = test_repo("ambiguous", 1000);
+        let mut prefixes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for id in &ids {
+            let hex = id.to_string();
+            prefixes.entry(hex[..4].to_string()).or_default().push(hex);
+        }
+        let (prefix, mut expected) = prefixes
+            .into_iter()
+            .find(|(_, hashes)| hashes.len() > 1)
+            .expect("no shared prefix among test commits");
+        match resolve_commit_in(&repo, &prefix) {
+            Err(Error::AmbiguousRevision {
+                spec,
+                mut candidates,
+            }) => {
+                assert_eq!(spec, prefix);
+                candidates.sort();
+                expected.sort();
+                assert_eq!(candidates, expected);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        let _ = std::fs::remove_dir_all(repo.path());
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::LazyLock;
+use std::{collections::BTreeMap, sync::LazyLock};
 
 use deadpool_sqlite::Pool;
 use git2::Repository;
@@ -21,8 +21,13 @@ pub struct RepoResources {
     pub cache: Pool,
     pub cache_update_lock: Mutex<()>,
     pub settings: RwLock<RepoSettings>,
+    // median latencies between pipeline stages, cleared when the cache is updated
+    pub latencies: Mutex<StageLatencies>,
 }
 
+// `None` if no recent commit has been seen in both stages
+pub type StageLatencies = BTreeMap<(String, String), Option<i64>>;
+
 impl Resource<String> for RepoResources {
     async fn open(name: &String) -> Result<Self, Error> {
         let paths = RepoPaths::new(name)?;
@@ -49,6 +54,7 @@ impl Resource<String> for RepoResources {
             cache,
             cache_update_lock: Mutex::new(()),
             settings,
+            latencies: Default::default(),
         })
     }
 }
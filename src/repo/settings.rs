@@ -3,7 +3,7 @@ use std::collections::BTreeMap;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::{condition::GeneralCondition, github::GitHubInfo};
+use crate::{condition::GeneralCondition, github::GitHubInfo, template::TemplateKind};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepoSettings {
@@ -13,6 +13,12 @@ pub struct RepoSettings {
     pub github_info: Option<GitHubInfo>,
     #[serde(default)]
     pub conditions: BTreeMap<String, ConditionSettings>,
+    // ordered branch stages commits go through
+    #[serde(default)]
+    pub pipeline: Vec<String>,
+    // default templates for chats tracking the repository
+    #[serde(default)]
+    pub templates: BTreeMap<TemplateKind, String>,
 }
 
 fn default_branch_regex() -> Regex {
@@ -25,6 +31,8 @@ impl Default for RepoSettings {
             branch_regex: default_branch_regex(),
             github_info: Default::default(),
             conditions: Default::default(),
+            pipeline: Default::default(),
+            templates: Default::default(),
         }
     }
 }
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use teloxide::utils::markdown;
+
+use crate::error::Error;
+
+// placeholder names and their markdown values
+pub type TemplateValues = Vec<(&'static str, String)>;
+
+#[derive(
+    clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TemplateKind {
+    Commit,
+    Branch,
+    PrIssue,
+    Watch,
+    BranchPair,
+}
+
+impl TemplateKind {
+    pub fn placeholders(self) -> &'static [&'static str] {
+        match self {
+            TemplateKind::Commit => &[
+                "repo",
+                "comment",
+                "url",
+                "commit",
+                "new",
+                "all",
+                "progress",
+                "subscribers",
+                "conditions",
+            ],
+            TemplateKind::Branch => &[
+                "repo",
+                "branch",
+                "comment",
+                "old",
+                "new",
+                "change",
+                "commits",
+                "subscribers",
+            ],
+            TemplateKind::PrIssue => &[
+                "repo",
+                "id",
+                "url",
+                "comment",
+                "status",
+                "commit",
+                "subscribers",
+            ],
+            TemplateKind::Watch => &["repo", "watch", "comment", "commit", "subscribers"],
+            TemplateKind::BranchPair => &[
+                "repo",
+                "branch",
+                "base",
+                "ahead",
+                "behind",
+                "comment",
+                "subscribers",
+            ],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Segment<'a> {
+    Text(String),
+    Placeholder(&'a str),
+}
+
+// `{name}` is a placeholder, `{{` and `}}` are literal braces
+fn parse(template: &str) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        text.push_str(&rest[..i]);
+        let brace = &rest[i..i + 1];
+        rest = &rest[i + 1..];
+        if let Some(after) = rest.strip_prefix(brace) {
+            text.push_str(brace);
+            rest = after;
+        } else if brace == "}" {
+            return Err("unmatched '}', use '}}' for a literal brace".to_string());
+        } else {
+            let end = rest
+                .find('}')
+                .ok_or_else(|| "unclosed '{', use '{{' for a literal brace".to_string())?;
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            segments.push(Segment::Placeholder(&rest[..end]));
+            rest = &rest[end + 1..];
+        }
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+pub fn validate(kind: TemplateKind, template: &str) -> Result<(), Error> {
+    let invalid = |reason: String| Error::InvalidTemplate {
+        template: template.to_string(),
+        reason,
+    };
+    let placeholders = kind.placeholders();
+    for segment in parse(template).map_err(invalid)? {
+        if let Segment::Placeholder(name) = segment
+            && !placeholders.contains(&name)
+        {
+            return Err(invalid(format!(
+                "unknown placeholder '{{{name}}}', available: {}",
+                placeholders.join(", ")
+            )));
+        }
+    }
+    Ok(())
+}
+
+// literal text is escaped, values are expected to be markdown already
+pub fn render(template: &str, values: &[(&str, String)]) -> String {
+    let segments = match parse(template) {
+        Ok(segments) => segments,
+        Err(reason) => {
+            // templates are validated when set, so this should not happen
+            log::warn!("invalid template {template:?}: {reason}");
+            return markdown::escape(template);
+        }
+    };
+    let mut result = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => result.push_str(&markdown::escape(&text)),
+            Segment::Placeholder(name) => {
+                if let Some((_, value)) = values.iter().find(|(n, _)| *n == name) {
+                    result.push_str(value);
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_placeholders() {
+        assert!(validate(TemplateKind::Commit, "[{repo}] {comment} +{new}").is_ok());
+        assert!(validate(TemplateKind::Commit, "{{literal}} braces").is_ok());
+        assert!(validate(TemplateKind::Commit, "{branch}").is_err());
+        assert!(validate(TemplateKind::Commit, "{repo").is_err());
+        assert!(validate(TemplateKind::Commit, "repo}").is_err());
+    }
+
+    #[test]
+    fn render_escaping() {
+        let values = [
+            ("repo", "my\\-repo".to_string()),
+            ("new", "`main`".to_string()),
+        ];
+        assert_eq!(
+            render("[{repo}] +{new} {{x}}.", &values),
+            "\\[my\\-repo\\] \\+`main` \\{x\\}\\."
+        );
+    }
+}
@@ -1,29 +1,37 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use teloxide::{
     Bot,
     payloads::SendMessageSetters,
     prelude::Requester,
-    sugar::request::RequestLinkPreviewExt,
     types::{ChatId, ParseMode},
+    utils::markdown,
 };
 
 use crate::{
     CommandError,
     chat::{
-        self,
+        self, Item,
         resources::ChatRepoResources,
-        results::PRIssueCheckResult,
-        settings::{BranchSettings, CommitSettings, PRIssueSettings},
+        results::{HistoryKind, Notification, PRIssueCheckResult},
+        settings::{
+            BranchPairSettings, BranchSettings, CommitSettings, PRIssueSettings, QuietMode,
+        },
     },
     condition::Action,
+    dashboard,
+    error::Error,
+    i18n::Key,
     message::{
-        branch_check_message, commit_check_message, pr_issue_closed_message,
-        pr_issue_merged_message, pr_issue_opened_message,
+        branch_check_message, branch_check_summary, branch_pair_check_message,
+        branch_pair_check_summary, branch_pair_template_values, branch_template_values,
+        commit_check_message, commit_check_summary, commit_template_values, mute_summary_message,
+        pr_issue_closed_message, pr_issue_merged_message, pr_issue_opened_message,
+        pr_issue_template_values, watch_check_message, watch_check_summary, watch_template_values,
     },
-    options,
+    notify, options,
     repo::{self, resources::RepoResources},
-    try_attach_subscribe_button_markup,
+    template::{self, TemplateKind},
 };
 
 pub async fn update_and_report_error(bot: Bot) -> Result<(), teloxide::RequestError> {
@@ -42,39 +50,128 @@ pub async fn update_and_report_error(bot: Bot) -> Result<(), teloxide::RequestEr
 
 async fn update(bot: Bot) -> Result<(), CommandError> {
     let repos = repo::list().await?;
+    let mut new_commits = BTreeMap::new();
     for repo in repos {
         log::info!("updating repository {repo}...");
         let resources = repo::resources(&repo).await?;
         log::info!("updating {repo}...");
-        if let Err(e) = repo::fetch_and_update_cache(resources).await {
-            log::error!("update error for repository {repo}: {e}");
+        match repo::fetch_and_update_cache(resources).await {
+            Ok(commits) => {
+                new_commits.insert(repo, commits);
+            }
+            Err(e) => log::error!("update error for repository {repo}: {e}"),
         }
     }
     log::info!("updating chats...");
     let chats = chat::chats().await?;
     for chat in chats {
-        if let Err(e) = update_chat(bot.clone(), chat).await {
+        let _guard = chat::update_lock(chat).await;
+        // denied after listing
+        if !chat::allowed(chat) {
+            continue;
+        }
+        if let Err(e) = update_chat(bot.clone(), chat, &new_commits).await {
             log::error!("update error for chat {chat}: {e}");
         }
     }
     Ok(())
 }
 
-async fn update_chat(bot: Bot, chat: ChatId) -> Result<(), CommandError> {
+async fn update_chat(
+    bot: Bot,
+    chat: ChatId,
+    new_commits: &BTreeMap<String, BTreeSet<String>>,
+) -> Result<(), CommandError> {
+    let chat_resources = chat::chat_resources(chat).await?;
+    let quiet = chat::quiet_mode(&chat_resources).await;
+    if let Some(mute) = chat::chat_mute_expired(&chat_resources).await {
+        let lang = chat::language(chat).await;
+        let message = mute_summary_message(lang, lang.text(Key::MuteTargetChat), &mute, true);
+        bot.send_message(chat, &message)
+            .parse_mode(ParseMode::MarkdownV2)
+            .disable_notification(quiet.is_some())
+            .await?;
+        chat::chat_mute_clear(&chat_resources).await?;
+        let summary = message.lines().next().unwrap_or_default().to_string();
+        chat::history_record_chat(chat, None, HistoryKind::Expired, summary).await;
+    }
+
+    // quiet hours ended, deliver held notifications first
+    if quiet != Some(QuietMode::Defer) {
+        for deferred in chat::deferred_take(&chat_resources).await? {
+            let repo = &deferred.repo;
+            if let Err(e) =
+                notify::deliver(&bot, chat, repo, deferred.notification, quiet.is_some()).await
+            {
+                log::error!("failed to deliver deferred notification ({chat}, {repo}): {e}");
+            }
+        }
+    }
+
     let repos = chat::repos(chat).await?;
     for repo in repos {
         log::info!("updating repository of chat ({chat}, {repo})...");
-        if let Err(e) = update_chat_repo(bot.clone(), chat, &repo).await {
+        let repo_new_commits = new_commits.get(&repo);
+        if let Err(e) = update_chat_repo(bot.clone(), chat, &repo, repo_new_commits).await {
             log::error!("update error for repository of chat ({chat}, {repo}): {e}");
         }
     }
+
+    // deliver the digest after events of this update are queued, postponed during deferring
+    // quiet hours
+    if quiet != Some(QuietMode::Defer)
+        && let Some((since, events)) = chat::digest_due(&chat_resources).await?
+    {
+        let summary = notify::send_digest(&bot, chat, since, &events, quiet.is_some()).await?;
+        chat::digest_clear(&chat_resources, events.len()).await?;
+        chat::history_record_chat(chat, None, HistoryKind::Sent, summary).await;
+    }
+
+    if let Err(e) = dashboard::refresh(&bot, chat).await {
+        log::error!("failed to refresh dashboard of chat {chat}: {e}");
+    }
     Ok(())
 }
 
-async fn update_chat_repo(bot: Bot, chat: ChatId, repo: &str) -> Result<(), CommandError> {
+async fn update_chat_repo(
+    bot: Bot,
+    chat: ChatId,
+    repo: &str,
+    new_commits: Option<&BTreeSet<String>>,
+) -> Result<(), CommandError> {
     log::info!("updating ({chat}, {repo})...");
     let resources = chat::resources_chat_repo(chat, repo.to_string()).await?;
     let repo_resources = repo::resources(repo).await?;
+    let chat_resources = chat::chat_resources(chat).await?;
+    let quiet = chat::quiet_mode(&chat_resources).await;
+
+    for (item, mute) in chat::item_mute_expired(&resources).await {
+        let target = markdown::escape(&format!("[{repo}] {item}"));
+        let lang = chat::language(chat).await;
+        let message = mute_summary_message(lang, &target, &mute, true);
+        bot.send_message(chat, &message)
+            .parse_mode(ParseMode::MarkdownV2)
+            .disable_notification(quiet.is_some())
+            .await?;
+        chat::item_mute_clear(&resources, &item).await?;
+        let summary = message.lines().next().unwrap_or_default().to_string();
+        chat::history_record(&resources, HistoryKind::Expired, summary).await;
+    }
+
+    // match watch rules before checking commits, newly tracked commits are checked in this update
+    if let Some(commits) = new_commits.filter(|c| !c.is_empty())
+        && let Err(e) = update_chat_repo_watch(
+            bot.clone(),
+            &resources,
+            &repo_resources,
+            chat,
+            repo,
+            commits,
+        )
+        .await
+    {
+        log::error!("update error for watch rules ({chat}, {repo}): {e}");
+    }
 
     // check pull requests/issues before checking commits
     let pr_issues = {
@@ -119,6 +216,27 @@ async fn update_chat_repo(bot: Bot, chat: ChatId, repo: &str) -> Result<(), Comm
         }
     }
 
+    // check branch pairs of the repo
+    let branch_pairs = {
+        let settings = resources.settings.read().await;
+        settings.branch_pairs.clone()
+    };
+    for (key, settings) in branch_pairs {
+        if let Err(e) = update_chat_repo_branch_pair(
+            bot.clone(),
+            &resources,
+            &repo_resources,
+            chat,
+            repo,
+            &key,
+            &settings,
+        )
+        .await
+        {
+            log::error!("update error for branch pair ({chat}, {repo}, {key}): {e}");
+        }
+    }
+
     // check commits of the repo
     let commits = {
         let settings = resources.settings.read().await;
@@ -143,6 +261,37 @@ async fn update_chat_repo(bot: Bot, chat: ChatId, repo: &str) -> Result<(), Comm
     Ok(())
 }
 
+async fn update_chat_repo_watch(
+    bot: Bot,
+    resources: &ChatRepoResources,
+    repo_resources: &RepoResources,
+    chat: ChatId,
+    repo: &str,
+    commits: &BTreeSet<String>,
+) -> Result<(), CommandError> {
+    let results = chat::watch_check(resources, repo_resources, commits).await?;
+    log::info!(
+        "finished watch rules check ({chat}, {repo}), {} commits tracked",
+        results.len()
+    );
+    let lang = chat::language(chat).await;
+    for mut result in results {
+        let private = notify::private_recipients(chat, &mut result.settings.notify).await?;
+        let message = match chat::template(chat, repo_resources, TemplateKind::Watch).await {
+            Some(t) => template::render(&t, &watch_template_values(lang, repo, &result)),
+            None => watch_check_message(lang, repo, &result),
+        };
+        let summary = watch_check_summary(repo, &result);
+        let mut notification =
+            Notification::new(Some(Item::Commit(result.commit)), &summary, message);
+        notification.set_recipients(&result.settings.notify, private);
+        notification.subscribe_button = true;
+        notification.link_preview = false;
+        notify::send(&bot, resources, notification).await?;
+    }
+    Ok(())
+}
+
 async fn update_chat_repo_pr_issue(
     bot: Bot,
     resources: &ChatRepoResources,
@@ -154,26 +303,16 @@ async fn update_chat_repo_pr_issue(
 ) -> Result<(), CommandError> {
     let result = chat::pr_issue_check(resources, repo_resources, id).await?;
     log::info!("finished PR/issue check ({chat}, {repo}, {id})");
+    let mut settings = settings.clone();
+    let private = notify::private_recipients(chat, &mut settings.notify).await?;
+    let settings = &settings;
     match result {
-        PRIssueCheckResult::Merged(commit) => {
-            let message = pr_issue_merged_message(repo_resources, id, settings, &commit).await?;
-            bot.send_message(chat, message)
-                .parse_mode(ParseMode::MarkdownV2)
-                .await?;
-            Ok(())
-        }
-        PRIssueCheckResult::Opened => {
-            let message = pr_issue_opened_message(repo_resources, id, settings).await?;
-            bot.send_message(chat, message)
-                .parse_mode(ParseMode::MarkdownV2)
-                .await?;
-            Ok(())
-        }
-        PRIssueCheckResult::Closed => {
-            let message = pr_issue_closed_message(repo_resources, id, settings).await?;
-            bot.send_message(chat, message)
-                .parse_mode(ParseMode::MarkdownV2)
-                .await?;
+        PRIssueCheckResult::Merged(_) | PRIssueCheckResult::Opened | PRIssueCheckResult::Closed => {
+            let message = pr_issue_message(chat, repo_resources, id, settings, &result).await?;
+            let summary = format!("[{repo}] #{id} {}", pr_issue_status(&result));
+            let mut notification = Notification::new(Some(Item::PRIssue(id)), &summary, message);
+            notification.set_recipients(&settings.notify, private);
+            notify::send(&bot, resources, notification).await?;
             Ok(())
         }
         PRIssueCheckResult::Waiting => Ok(()),
@@ -189,6 +328,43 @@ async fn update_chat_repo_pr_issue(
     }
 }
 
+async fn pr_issue_message(
+    chat: ChatId,
+    repo_resources: &RepoResources,
+    id: u64,
+    settings: &PRIssueSettings,
+    result: &PRIssueCheckResult,
+) -> Result<String, Error> {
+    let lang = chat::language(chat).await;
+    let status = pr_issue_status(result);
+    let commit = match result {
+        PRIssueCheckResult::Merged(commit) => Some(commit.as_str()),
+        _ => None,
+    };
+    if let Some(t) = chat::template(chat, repo_resources, TemplateKind::PrIssue).await {
+        let values =
+            pr_issue_template_values(lang, repo_resources, id, settings, status, commit).await?;
+        return Ok(template::render(&t, &values));
+    }
+    match result {
+        PRIssueCheckResult::Merged(commit) => {
+            pr_issue_merged_message(lang, repo_resources, id, settings, commit).await
+        }
+        PRIssueCheckResult::Opened => {
+            pr_issue_opened_message(lang, repo_resources, id, settings).await
+        }
+        _ => pr_issue_closed_message(lang, repo_resources, id, settings).await,
+    }
+}
+
+fn pr_issue_status(result: &PRIssueCheckResult) -> &'static str {
+    match result {
+        PRIssueCheckResult::Merged(_) => "merged",
+        PRIssueCheckResult::Opened => "opened",
+        _ => "closed",
+    }
+}
+
 async fn update_chat_repo_commit(
     bot: Bot,
     resources: &ChatRepoResources,
@@ -206,17 +382,26 @@ async fn update_chat_repo_commit(
         if !suppress_notification_conditions.is_empty() {
             log::info!("suppress notification for check result of ({chat}, {repo}): {result:?}",);
         } else {
+            let mut settings = settings.clone();
+            let private = notify::private_recipients(chat, &mut settings.notify).await?;
+            let lang = chat::language(chat).await;
             // mention in update
-            let message = commit_check_message(repo, commit, settings, &result, true);
-            let mut send = bot
-                .send_message(chat, message)
-                .parse_mode(ParseMode::MarkdownV2)
-                .disable_link_preview(true);
+            let message = match chat::template(chat, repo_resources, TemplateKind::Commit).await {
+                Some(t) => {
+                    let values = commit_template_values(lang, repo, commit, &settings, &result);
+                    template::render(&t, &values)
+                }
+                None => commit_check_message(lang, repo, commit, &settings, &result, true),
+            };
+            let summary = commit_check_summary(repo, &settings, &result);
+            let mut notification =
+                Notification::new(Some(Item::Commit(commit.to_string())), &summary, message);
+            notification.set_recipients(&settings.notify, private);
             let remove_conditions: BTreeSet<&String> = result.conditions_of_action(Action::Remove);
-            if remove_conditions.is_empty() {
-                send = try_attach_subscribe_button_markup(chat, send, "c", repo, commit);
-            }
-            send.await?;
+            notification.subscribe_button = remove_conditions.is_empty();
+            notification.link_preview = false;
+            notification.living = settings.living;
+            notify::send(&bot, resources, notification).await?;
         }
     }
     Ok(())
@@ -233,23 +418,61 @@ async fn update_chat_repo_branch(
 ) -> Result<(), CommandError> {
     let result = chat::branch_check(resources, repo_resources, branch).await?;
     log::info!("finished branch check ({chat}, {repo}, {branch})");
-    if result.new != result.old {
+    if result.should_notify(settings) || result.stale.is_some() {
+        let mut settings = settings.clone();
+        let private = notify::private_recipients(chat, &mut settings.notify).await?;
+        let lang = chat::language(chat).await;
+        let template = chat::template(chat, repo_resources, TemplateKind::Branch).await;
         let message = {
             let repo_settings = repo_resources.settings.read().await;
-            branch_check_message(
-                repo,
-                branch,
-                settings,
-                &result,
-                repo_settings.github_info.as_ref(),
-            )
+            let github_info = repo_settings.github_info.as_ref();
+            match template {
+                Some(t) => {
+                    let values =
+                        branch_template_values(lang, repo, branch, &settings, &result, github_info);
+                    template::render(&t, &values)
+                }
+                None => branch_check_message(lang, repo, branch, &settings, &result, github_info),
+            }
         };
-        let mut send = bot
-            .send_message(chat, message)
-            .parse_mode(ParseMode::MarkdownV2)
-            .disable_link_preview(true);
-        send = try_attach_subscribe_button_markup(chat, send, "b", repo, branch);
-        send.await?;
+        let summary = branch_check_summary(repo, branch, &result);
+        let mut notification =
+            Notification::new(Some(Item::Branch(branch.to_string())), &summary, message);
+        notification.set_recipients(&settings.notify, private);
+        notification.subscribe_button = true;
+        notification.link_preview = false;
+        notification.living = settings.living;
+        notify::send(&bot, resources, notification).await?;
+    }
+    Ok(())
+}
+
+async fn update_chat_repo_branch_pair(
+    bot: Bot,
+    resources: &ChatRepoResources,
+    repo_resources: &RepoResources,
+    chat: ChatId,
+    repo: &str,
+    key: &str,
+    settings: &BranchPairSettings,
+) -> Result<(), CommandError> {
+    let result = chat::branch_pair_check(resources, repo_resources, key).await?;
+    log::info!("finished branch pair check ({chat}, {repo}, {key})");
+    if result.event.is_some() {
+        let mut settings = settings.clone();
+        let private = notify::private_recipients(chat, &mut settings.notify).await?;
+        let lang = chat::language(chat).await;
+        let message = match chat::template(chat, repo_resources, TemplateKind::BranchPair).await {
+            Some(t) => {
+                let values = branch_pair_template_values(lang, repo, &settings, &result);
+                template::render(&t, &values)
+            }
+            None => branch_pair_check_message(lang, repo, &settings, &result),
+        };
+        let summary = branch_pair_check_summary(repo, &settings, &result);
+        let mut notification = Notification::new(None, &summary, message);
+        notification.set_recipients(&settings.notify, private);
+        notify::send(&bot, resources, notification).await?;
     }
     Ok(())
 }
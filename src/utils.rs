@@ -66,6 +66,10 @@ pub fn empty_or_start_new_line(s: &str) -> String {
     }
 }
 
+pub fn first_line(text: &str) -> &str {
+    text.trim().lines().next().unwrap_or_default()
+}
+
 pub fn read_json<P, T>(path: P) -> Result<T, Error>
 where
     P: AsRef<Path> + fmt::Debug,
@@ -115,16 +119,21 @@ pub fn modify_subscriber_set(
     subscriber: Subscriber,
     unsubscribe: bool,
 ) -> Result<(), Error> {
-    if unsubscribe {
-        if !set.contains(&subscriber) {
-            return Err(Error::NotSubscribed);
+    let existing = set.iter().find(|s| s.same_user(&subscriber)).cloned();
+    match existing {
+        None if unsubscribe => return Err(Error::NotSubscribed),
+        Some(existing) if unsubscribe => {
+            set.remove(&existing);
         }
-        set.remove(&subscriber);
-    } else {
-        if set.contains(&subscriber) {
-            return Err(Error::AlreadySubscribed);
+        Some(existing) if existing == subscriber => return Err(Error::AlreadySubscribed),
+        Some(existing) => {
+            // refresh outdated mention or user id
+            set.remove(&existing);
+            set.insert(subscriber);
+        }
+        None => {
+            set.insert(subscriber);
         }
-        set.insert(subscriber);
     }
     Ok(())
 }